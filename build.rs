@@ -30,12 +30,31 @@ fn main() {
         .read_to_string(&mut contents)
         .unwrap();
 
-    let new_contents = format!("pub mod krpc {{\n{contents}\n}}");
+    // Generated code may allow lints this toolchain no longer knows about.
+    let new_contents = format!(
+        "#[allow(renamed_and_removed_lints)]\npub mod krpc {{\n{contents}\n}}"
+    );
     File::create(proto_path.join("krpc.rs"))
         .unwrap()
         .write_all(new_contents.as_bytes())
         .unwrap();
 
-    let mut f = File::create(proto_path.join("services.rs")).unwrap();
-    krpc_build::build("service_definitions/", &mut f).unwrap();
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_GEOMETRY");
+    println!("cargo:rerun-if-env-changed=CARGO_FEATURE_NAMED_TUPLES");
+    let config = krpc_build::BuildConfig {
+        geometry_types: env::var_os("CARGO_FEATURE_GEOMETRY").is_some(),
+        named_tuples: env::var_os("CARGO_FEATURE_NAMED_TUPLES").is_some(),
+        // Recorded in the crate version's build metadata, e.g.
+        // `0.5.1-api+0.5.2`.
+        krpc_version: env!("CARGO_PKG_VERSION")
+            .split_once('+')
+            .map(|(_, version)| version.into()),
+        ..Default::default()
+    };
+    krpc_build::build_to_dir(
+        "service_definitions/",
+        &config,
+        proto_path.join("services"),
+    )
+    .unwrap_or_else(|e| panic!("failed to generate services: {e}"));
 }
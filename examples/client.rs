@@ -10,16 +10,14 @@ fn main() -> Result<(), Box<dyn Error>> {
     let sc = SpaceCenter::new(client.clone());
 
     // Check out our vessel.
-    let ship = sc.get_active_vessel()?;
+    let ship = sc.active_vessel()?;
 
     // Greet the crew.
-    match ship.get_crew()?.first() {
-        Some(kerbal) => println!(
-            "Hello, {}. Welcome to {}",
-            kerbal.get_name()?,
-            ship.get_name()?
-        ),
-        None => println!("{} is unkerbaled!", ship.get_name()?),
+    match ship.crew()?.first() {
+        Some(kerbal) => {
+            println!("Hello, {}. Welcome to {}", kerbal.name()?, ship.name()?)
+        }
+        None => println!("{} is unkerbaled!", ship.name()?),
     };
 
     Ok(())
@@ -2,7 +2,7 @@ use std::error::Error;
 
 use krpc_client::{client::Client, services::space_center::SpaceCenter};
 
-/// This example creates a stream of get_ut(), awaiting
+/// This example creates a stream of ut(), awaiting
 /// updates at a rate of 1Hz.
 fn main() -> Result<(), Box<dyn Error>> {
     let client = Client::new("kRPC TEST", "127.0.0.1", 50000, 50001)?;
@@ -10,7 +10,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let space_center = SpaceCenter::new(client.clone());
 
     // Set up a stream.
-    let ut_stream = space_center.get_ut_stream()?;
+    let ut_stream = space_center.ut_stream()?;
     ut_stream.set_rate(1f32)?;
 
     // Wait for updates, and print the current value.
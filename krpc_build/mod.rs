@@ -1,4 +1,8 @@
-use std::{fs, io, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+};
 
 use convert_case::{Case, Casing};
 use proc_macro2::TokenStream;
@@ -6,12 +10,87 @@ use quote::{format_ident, quote, ToTokens};
 use serde_json::Value;
 use syn::Ident;
 
-type TokenSet<'a> = Box<dyn Iterator<Item = TokenStream> + 'a>;
+type Result<T> = std::result::Result<T, io::Error>;
 
-/// Generate source code from JSON service definitions.
+/// Options for code generation.
+#[derive(Default)]
+pub struct BuildConfig {
+    /// Rust types to generate in place of kRPC types, keyed
+    /// by `Service.Name` for classes and enumerations, or by
+    /// type code (e.g. `DOUBLE`) for everything else.
+    ///
+    /// Overrides take precedence over the default mapping, and
+    /// the replacement must implement the same encoding and
+    /// decoding traits as the type it replaces.
+    pub type_overrides: HashMap<String, String>,
+
+    /// Return `Vector3` and `Quaternion` in place of tuples of
+    /// doubles, from procedures documented as returning a
+    /// vector or a quaternion.
+    pub geometry_types: bool,
+
+    /// Procedures whose results are fetched once and then
+    /// reused, keyed by `Service.Procedure` (e.g.
+    /// `SpaceCenter.get_G`). Each must be a service procedure
+    /// without parameters, and [`build`] and [`build_to_dir`]
+    /// fail on any not among their definitions.
+    ///
+    /// Only safe for values that never change while the client
+    /// is connected. Each service struct holds its own cache.
+    pub cached_procedures: HashSet<String>,
+
+    /// Procedures whose results are fetched once and then
+    /// reused until the service's `invalidate` method is
+    /// called, keyed like `cached_procedures`. Each must be a
+    /// service procedure without parameters, returning a class,
+    /// and is checked against the definitions in the same way.
+    ///
+    /// The object returned can go stale: after switching
+    /// vessels, `SpaceCenter.get_ActiveVessel` still returns
+    /// the old one. Call `invalidate` whenever that may have
+    /// happened, such as after a scene change.
+    pub memoized_procedures: HashSet<String>,
+
+    /// Generate every service unconditionally, rather than
+    /// behind a Cargo feature named after its module (e.g.
+    /// `space_center`).
+    ///
+    /// The `KRPC` service is never gated, as the client
+    /// itself depends on it.
+    pub ungated_services: bool,
+
+    /// Return a struct with named fields in place of a tuple,
+    /// from each procedure returning one, e.g.
+    /// `FlightAngularVelocity { x, y, z }` for
+    /// `Flight::angular_velocity`. Tuples of up to four
+    /// elements have fields `x`, `y`, `z` and `w`, and longer
+    /// ones `item_0` and so on.
+    ///
+    /// Geometry types take precedence, if enabled.
+    pub named_tuples: bool,
+
+    /// The path to the client's runtime, i.e. its `schema`,
+    /// `client`, `stream` and `error` modules. Defaults to
+    /// `crate`; set it to `krpc_client` to generate services
+    /// into another crate, depending on this one.
+    ///
+    /// Services refer to each other relatively, so their
+    /// modules must be declared side by side, as the output
+    /// of [`build_to_dir`] is.
+    pub runtime_path: Option<String>,
+
+    /// The kRPC version the definitions were taken from, for
+    /// definitions without a `version` of their own. Generated
+    /// as `KRPC_VERSION`, which is `None` if neither gives it.
+    pub krpc_version: Option<String>,
+}
+
+/// Generate source code from a directory of JSON service
+/// definitions.
 ///
 /// Creates a module for each RPC service that contains all
-/// type and function definitions for that service.
+/// type and function definitions for that service, and a
+/// `Services` struct with an accessor for each service.
 ///
 /// # Examples
 /// ```json
@@ -32,212 +111,1094 @@ type TokenSet<'a> = Box<dyn Iterator<Item = TokenStream> + 'a>;
 /// ```
 /// becomes
 /// ```rust
-/// use std::sync::Arc;
-///
-/// use crate::{client::Client, error::RpcError, schema::rpc_object};
+/// use crate::{error::RpcError, schema::rpc_object, ClientHandle};
 ///
 /// pub mod space_center {
 ///     rpc_object!(Vessel);
 ///
 ///     pub struct SpaceCenter {
-///         pub client: Arc<Client>,
+///         pub client: ClientHandle,
 ///     }
 ///
 ///     impl SpaceCenter {
-///         pub fn get_active_vessel() -> Result<Vessel, RpcError> { ... }
+///         pub fn active_vessel() -> Result<Vessel, RpcError> { ... }
 ///     }
 /// }
+///
+/// pub struct Services { ... }
+///
+/// impl Services {
+///     pub fn space_center(&self) -> space_center::SpaceCenter { ... }
+/// }
 /// ```
+///
+/// # Errors
+///
+/// Fails if a definition cannot be read, or is malformed. The
+/// error message names the file, service, and key at fault.
+// The crate's own build script only uses `build_to_dir`.
+#[allow(dead_code)]
 pub fn build<O: io::Write>(
     service_definitions: impl AsRef<Path>,
+    config: &BuildConfig,
     out: &mut O,
-) -> Result<(), io::Error> {
-    for service_definition_path in fs::read_dir(service_definitions)? {
-        let service_definition_file =
-            fs::File::open(service_definition_path.unwrap().path())?;
+) -> Result<()> {
+    let mut services = Vec::new();
+    for service_definition_path in read_definitions(service_definitions)? {
+        services.extend(build_from_file(service_definition_path, config, out)?);
+    }
+    check_cached_procedures(&services, config)?;
+
+    write!(
+        out,
+        "{}",
+        render(generate_services_accessor(&services, config)?)?
+    )?;
+    write!(out, "{}", render(generate_prelude(&services))?)?;
+    write!(out, "{}", render(generate_version(&services, config)?)?)?;
+    write!(out, "{}", generate_feature_list(&services))?;
+    Ok(())
+}
+
+/// Generate source code from a directory of JSON service
+/// definitions, like [`build`], but writing each service's
+/// module to its own file in `out_dir`.
+///
+/// `out_dir` is created if needed, and gets a `mod.rs`
+/// declaring every service module along with the `Services`
+/// struct. Either include or declare that file as a module.
+/// Splitting the output keeps each file small enough to
+/// navigate.
+///
+/// # Errors
+///
+/// Fails if a definition cannot be read, or is malformed, or
+/// if the output cannot be written.
+pub fn build_to_dir(
+    service_definitions: impl AsRef<Path>,
+    config: &BuildConfig,
+    out_dir: impl AsRef<Path>,
+) -> Result<()> {
+    fs::create_dir_all(&out_dir)?;
+    // Module paths are relative to the file declaring them,
+    // which may be elsewhere if `mod.rs` is included.
+    let out_dir = out_dir.as_ref().canonicalize()?;
+
+    let mut services = Vec::new();
+    let mut declarations = Vec::new();
+    for service_definition_path in read_definitions(service_definitions)? {
+        let path = service_definition_path.display();
+        let service_definition_file = fs::File::open(&service_definition_path)
+            .map_err(|e| context(e, &path))?;
+
+        for (service, contents) in
+            generate_services(service_definition_file, config)
+                .map_err(|e| context(e, &path))?
+        {
+            let mod_name = service.name.to_case(Case::Snake);
+            let mod_path = out_dir.join(format!("{mod_name}.rs"));
+            fs::write(&mod_path, render(contents)?)?;
+
+            let mod_name = format_ident!("{mod_name}");
+            let mod_path = mod_path.to_str().ok_or_else(|| {
+                invalid(format!("non UTF-8 path: {}", mod_path.display()))
+            })?;
+            let attributes = service.attributes();
+            declarations.push(quote! {
+                #attributes
+                #[path = #mod_path]
+                pub mod #mod_name;
+            });
+            services.push(service);
+        }
+    }
+
+    check_cached_procedures(&services, config)?;
+    let accessor = generate_services_accessor(&services, config)?;
+    let prelude = generate_prelude(&services);
+    let version = generate_version(&services, config)?;
+    let mut mod_rs = render(quote! {
+        #(#declarations)*
+        #accessor
+        #prelude
+        #version
+    })?;
+    mod_rs.push_str(&generate_feature_list(&services));
+    fs::write(out_dir.join("mod.rs"), mod_rs)
+}
+
+/// The types in a set of service definitions that cannot be
+/// generated, as found by [`build_report`].
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    /// The procedures using each type code that can't be
+    /// generated, as `Service.Procedure`.
+    pub unsupported: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Find every type code in a directory of service definitions
+/// that [`build`] would fail to generate, along with the
+/// procedures using it, without generating anything.
+///
+/// Codes covered by a type override in `config` are supported.
+///
+/// # Errors
+///
+/// Fails if a definition cannot be read, or its procedures are
+/// malformed.
+// The crate's own build script only uses `build_to_dir`.
+#[allow(dead_code)]
+pub fn build_report(
+    service_definitions: impl AsRef<Path>,
+    config: &BuildConfig,
+) -> Result<BuildReport> {
+    let mut report = BuildReport::default();
+    for service_definition_path in read_definitions(service_definitions)? {
+        let path = service_definition_path.display();
+        let service_definition_file = fs::File::open(&service_definition_path)
+            .map_err(|e| context(e, &path))?;
         let service_definition_json: Value =
-            serde_json::from_reader(service_definition_file)?;
+            serde_json::from_reader(service_definition_file)
+                .map_err(|e| context(e.into(), &path))?;
 
         for (service_name, service_definition) in
-            service_definition_json.as_object().unwrap().into_iter()
+            as_object(&service_definition_json)
+                .map_err(|e| context(e, &path))?
         {
-            let service_module =
-                generate_module_definition(service_name, service_definition);
+            let procedures = get_object(service_definition, "procedures")
+                .map_err(|e| context(e, &path))?;
+            for (name, definition) in procedures {
+                let params = get_array(definition, "parameters")
+                    .map_err(|e| context(e, &path))?;
+                let types = params
+                    .iter()
+                    .filter_map(|param| param.get("type"))
+                    .chain(get_return_value(definition));
+                let mut codes = BTreeSet::new();
+                for ty in types {
+                    find_unsupported(ty, config, &mut codes);
+                }
+                for code in codes {
+                    report
+                        .unsupported
+                        .entry(code)
+                        .or_default()
+                        .insert(format!("{service_name}.{name}"));
+                }
+            }
+        }
+    }
+    Ok(report)
+}
+
+/// Collect the codes of the types within `ty` that can't be
+/// generated. A type only counts when its own code is at
+/// fault, rather than one of the types it contains.
+fn find_unsupported(
+    ty: &Value,
+    config: &BuildConfig,
+    codes: &mut BTreeSet<String>,
+) {
+    let children = ty
+        .get("types")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut children_supported = true;
+    for child in children {
+        children_supported &= decode_type(child, false, false, config).is_ok();
+        find_unsupported(child, config, codes);
+    }
+    if children_supported && decode_type(ty, false, false, config).is_err() {
+        let code = ty.get("code").and_then(Value::as_str).unwrap_or("");
+        codes.insert(code.into());
+    }
+}
+
+/// List the JSON files in a directory of service definitions.
+fn read_definitions(
+    service_definitions: impl AsRef<Path>,
+) -> Result<Vec<PathBuf>> {
+    // Directory order is unspecified, so sort for reproducible
+    // output. Definitions themselves are read into sorted maps.
+    let mut service_definition_paths = fs::read_dir(service_definitions)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>>>()?;
+    service_definition_paths.sort();
+    Ok(service_definition_paths)
+}
 
-            #[cfg(feature = "fmt")]
-            let service_module =
-                prettyplease::unparse(&syn::parse2(service_module).unwrap());
+/// Format generated code, if the `fmt` feature is enabled.
+fn render(tokens: TokenStream) -> Result<String> {
+    #[cfg(feature = "fmt")]
+    return Ok(prettyplease::unparse(
+        &syn::parse2(tokens).map_err(invalid)?,
+    ));
+    #[cfg(not(feature = "fmt"))]
+    Ok(tokens.to_string())
+}
+
+/// Generate source code from a single JSON file, which may
+/// define any number of services, returning the services
+/// generated.
+///
+/// See [`build`] for the expected format.
+pub fn build_from_file<O: io::Write>(
+    service_definition: impl AsRef<Path>,
+    config: &BuildConfig,
+    out: &mut O,
+) -> Result<Vec<Service>> {
+    let path = service_definition.as_ref().display();
+    let service_definition_file =
+        fs::File::open(&service_definition).map_err(|e| context(e, &path))?;
+    build_from_reader(service_definition_file, config, out)
+        .map_err(|e| context(e, &path))
+}
+
+/// Generate source code from a JSON document, which may
+/// define any number of services, returning the services
+/// generated.
+///
+/// See [`build`] for the expected format.
+pub fn build_from_reader<R: io::Read, O: io::Write>(
+    service_definition: R,
+    config: &BuildConfig,
+    out: &mut O,
+) -> Result<Vec<Service>> {
+    let mut services = Vec::new();
+    for (service, contents) in generate_services(service_definition, config)? {
+        let service_mod_name =
+            format_ident!("{}", service.name.to_case(Case::Snake));
+        let attributes = service.attributes();
+        let service_module = quote! {
+            #attributes
+            pub mod #service_mod_name {
+                #contents
+            }
+        };
+        write!(out, "{}", render(service_module)?)?;
+        services.push(service);
+    }
+    Ok(services)
+}
 
-            write!(out, "{service_module}").unwrap();
+/// Generate the contents of each service's module from a JSON
+/// document, along with the service itself.
+fn generate_services<R: io::Read>(
+    service_definition: R,
+    config: &BuildConfig,
+) -> Result<Vec<(Service, TokenStream)>> {
+    let service_definition_json: Value =
+        serde_json::from_reader(service_definition)?;
+
+    as_object(&service_definition_json)?
+        .iter()
+        .map(|(service_name, service_definition)| {
+            let contents = generate_module_contents(
+                service_name,
+                service_definition,
+                config,
+            )?;
+            let service =
+                Service::new(service_name, service_definition, config);
+            Ok((service, contents))
+        })
+        .collect()
+}
+
+/// A service generated by [`build_from_reader`].
+pub struct Service {
+    /// The service's kRPC name, e.g. `SpaceCenter`.
+    pub name: String,
+    /// The Cargo feature enabling the service, unless it is
+    /// generated unconditionally.
+    pub feature: Option<String>,
+    /// The features of other services this one uses types
+    /// from.
+    pub dependencies: BTreeSet<String>,
+    docs: Vec<String>,
+    enums: Vec<String>,
+    procedures: Vec<String>,
+    version: Option<String>,
+}
+
+impl Service {
+    fn new(name: &str, definition: &Value, config: &BuildConfig) -> Self {
+        let feature = |name: &str| {
+            (!config.ungated_services && name != "KRPC")
+                .then(|| name.to_case(Case::Snake))
+        };
+
+        let mut referenced = BTreeSet::new();
+        find_services(definition, &mut referenced);
+        referenced.remove(name);
+
+        Self {
+            name: name.into(),
+            feature: feature(name),
+            dependencies: referenced
+                .iter()
+                .filter_map(|service| feature(service))
+                .collect(),
+            docs: get_docs(definition),
+            enums: definition
+                .get("enumerations")
+                .and_then(Value::as_object)
+                .map(|enums| enums.keys().cloned().collect())
+                .unwrap_or_default(),
+            procedures: definition
+                .get("procedures")
+                .and_then(Value::as_object)
+                .map(|procedures| procedures.keys().cloned().collect())
+                .unwrap_or_default(),
+            version: definition
+                .get("version")
+                .and_then(Value::as_str)
+                .map(String::from),
+        }
+    }
+
+    /// The attributes of the service's module.
+    fn attributes(&self) -> TokenStream {
+        let cfg = self.cfg();
+        quote! {
+            #cfg
+            #[allow(
+                clippy::type_complexity,
+                clippy::mutable_key_type,
+                clippy::double_must_use
+            )]
+        }
+    }
+
+    /// The `cfg` attribute gating the service, if any.
+    fn cfg(&self) -> Option<TokenStream> {
+        self.feature
+            .as_ref()
+            .map(|feature| quote!(#[cfg(feature = #feature)]))
+    }
+}
+
+/// Fail if a cached or memoized procedure is not defined by
+/// any of the services generated, as it is most likely
+/// misspelt.
+fn check_cached_procedures(
+    services: &[Service],
+    config: &BuildConfig,
+) -> Result<()> {
+    let defined = services
+        .iter()
+        .flat_map(|service| {
+            service
+                .procedures
+                .iter()
+                .map(|procedure| format!("{}.{procedure}", service.name))
+        })
+        .collect::<HashSet<_>>();
+    for (kind, procedures) in [
+        ("cached", &config.cached_procedures),
+        ("memoized", &config.memoized_procedures),
+    ] {
+        let undefined = procedures
+            .iter()
+            .filter(|name| !defined.contains(*name))
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>();
+        if !undefined.is_empty() {
+            return Err(invalid(format!(
+                "{kind} procedures are not defined: {}",
+                undefined.into_iter().collect::<Vec<_>>().join(", ")
+            )));
         }
     }
     Ok(())
 }
 
-fn generate_module_definition(
+/// Collect the names of services whose types appear anywhere
+/// in a definition.
+fn find_services(json: &Value, services: &mut BTreeSet<String>) {
+    match json {
+        Value::Object(object) => {
+            if let Some(Value::String(service)) = object.get("service") {
+                services.insert(service.clone());
+            }
+            object.values().for_each(|v| find_services(v, services));
+        }
+        Value::Array(array) => {
+            array.iter().for_each(|v| find_services(v, services))
+        }
+        _ => {}
+    }
+}
+
+/// Generate a comment suggesting a Cargo feature for each
+/// gated service, with the features it depends on.
+fn generate_feature_list(services: &[Service]) -> String {
+    let features = services
+        .iter()
+        .filter_map(|service| {
+            let feature = service.feature.as_ref()?;
+            let dependencies = service
+                .dependencies
+                .iter()
+                .map(|dependency| format!("\"{dependency}\""))
+                .collect::<Vec<_>>()
+                .join(", ");
+            Some(format!("// {feature} = [{dependencies}]\n"))
+        })
+        .collect::<String>();
+    if features.is_empty() {
+        return features;
+    }
+    format!(
+        "\n// Services are gated behind Cargo features, for example:\n\
+         //\n// [features]\n{features}"
+    )
+}
+
+/// Generate a struct giving access to every service from one
+/// client.
+fn generate_services_accessor(
+    services: &[Service],
+    config: &BuildConfig,
+) -> Result<TokenStream> {
+    let runtime = get_runtime_path(config)?;
+    let accessors = services.iter().map(|service| {
+        let mod_name = format_ident!("{}", service.name.to_case(Case::Snake));
+        let service_name = format_ident!("{}", service.name);
+        let docs = &service.docs;
+        let cfg = service.cfg();
+        quote! {
+            #cfg
+            #(#[doc = #docs])*
+            pub fn #mod_name(&self) -> #mod_name::#service_name {
+                #mod_name::#service_name::new(self.client.clone())
+            }
+        }
+    });
+
+    // The example needs the SpaceCenter service, which may be
+    // gated.
+    let example = services
+        .iter()
+        .find(|service| service.name == "SpaceCenter")
+        .map(|service| {
+            let fence = match &service.feature {
+                Some(feature) => quote! {
+                    #[cfg_attr(feature = #feature, doc = "```no_run")]
+                    #[cfg_attr(not(feature = #feature), doc = "```ignore")]
+                },
+                None => quote!(#[doc = "```no_run"]),
+            };
+            quote! {
+                ///
+                #fence
+                /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+                /// use krpc_client::{services::Services, Client};
+                /// let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
+                /// let services = Services::new(client);
+                /// println!("{}", services.space_center().active_vessel()?.name()?);
+                /// # Ok(())
+                /// # }
+                /// ```
+            }
+        });
+
+    Ok(quote! {
+        /// Every RPC service, constructed from a single client.
+        ///
+        /// Each accessor is cheap, only cloning the client.
+        #example
+        #[derive(Clone)]
+        pub struct Services {
+            client: #runtime::ClientHandle,
+        }
+
+        impl Services {
+            pub fn new(client: #runtime::ClientHandle) -> Self {
+                Self { client }
+            }
+
+            #(#accessors)*
+        }
+    })
+}
+
+/// Generate a module re-exporting every service struct and
+/// enumeration. Names used by more than one service are left
+/// out, rather than have one hide the other.
+fn generate_prelude(services: &[Service]) -> TokenStream {
+    let items = services
+        .iter()
+        .flat_map(|service| {
+            let mod_name = service.name.to_case(Case::Snake);
+            std::iter::once(&service.name)
+                .chain(&service.enums)
+                .map(move |name| (service, mod_name.clone(), name))
+        })
+        .collect::<Vec<_>>();
+
+    let mut uses = HashMap::<&str, usize>::new();
+    for (_, _, name) in &items {
+        *uses.entry(name.as_str()).or_default() += 1;
+    }
+
+    let exports = items
+        .iter()
+        .filter(|(_, _, name)| uses[name.as_str()] == 1)
+        .map(|(service, mod_name, name)| {
+            let cfg = service.cfg();
+            let mod_name = format_ident!("{mod_name}");
+            let name = format_ident!("{name}");
+            quote! {
+                #cfg
+                pub use super::#mod_name::#name;
+            }
+        });
+
+    quote! {
+        /// Every service, and the enumerations they define.
+        pub mod prelude {
+            #(#exports)*
+        }
+    }
+}
+
+/// Generate a constant of the kRPC version the services were
+/// generated from, if known.
+///
+/// Fails if the definitions are from different versions.
+fn generate_version(
+    services: &[Service],
+    config: &BuildConfig,
+) -> Result<TokenStream> {
+    let versions = services
+        .iter()
+        .filter_map(|service| service.version.as_deref())
+        .collect::<BTreeSet<_>>();
+    if versions.len() > 1 {
+        return Err(invalid(format!(
+            "service definitions are from different kRPC versions: {}",
+            versions.into_iter().collect::<Vec<_>>().join(", ")
+        )));
+    }
+    let version =
+        match versions.first().copied().or(config.krpc_version.as_deref()) {
+            Some(version) => quote!(Some(#version)),
+            None => quote!(None),
+        };
+    Ok(quote! {
+        /// The kRPC version these services were generated from,
+        /// if known.
+        pub const KRPC_VERSION: Option<&str> = #version;
+    })
+}
+
+/// Generate the items of a service's module.
+fn generate_module_contents(
     service_name: &str,
     service_definition: &Value,
-) -> TokenStream {
-    let service_mod_name =
-        format_ident!("{}", service_name.to_case(Case::Snake));
+    config: &BuildConfig,
+) -> Result<TokenStream> {
     let q_service_name = format_ident!("{}", service_name);
+    let runtime = get_runtime_path(config)?;
 
-    let classes = generate_class_definitions(service_definition);
-    let enums = generate_enum_definitions(service_definition);
+    let in_service = |e| context(e, format_args!("service `{service_name}`"));
+    let classes = generate_class_definitions(service_definition, config)
+        .map_err(in_service)?;
+    let errors =
+        generate_error_definition(service_definition, service_name, config)
+            .map_err(in_service)?;
+    let enums = generate_enum_definitions(service_definition, config)
+        .map_err(in_service)?;
+    let methods = get_object(service_definition, "procedures")
+        .map(get_methods)
+        .map_err(in_service)?;
     let procedures = generate_procedure_definitions(
         service_definition,
+        &methods,
         service_name,
         &q_service_name,
-    );
+        config,
+    )
+    .map_err(in_service)?;
+    let tuples = generate_tuple_definitions(
+        service_definition,
+        &methods,
+        service_name,
+        config,
+    )
+    .map_err(in_service)?;
+    let cache_fields = generate_cache_fields(
+        service_definition,
+        &methods,
+        service_name,
+        config,
+    )
+    .map_err(in_service)?;
+    let memoized = cache_fields
+        .iter()
+        .filter(|field| field.memoized)
+        .map(|field| &field.name)
+        .collect::<Vec<_>>();
+    let invalidate = (!memoized.is_empty()).then(|| {
+        quote! {
+            /// Forget the objects memoized by this service, so
+            /// that they are fetched again on next use.
+            pub fn invalidate(&self) {
+                #(*self.#memoized.lock().unwrap_or_else(::std::sync::PoisonError::into_inner) = None;)*
+            }
+        }
+    });
+    let (cache_names, cache_types): (Vec<_>, Vec<_>) = cache_fields
+        .into_iter()
+        .map(|field| (field.name, field.ty))
+        .unzip();
 
-    let arc_client = quote! {
-        ::std::sync::Arc<crate::Client>
+    let client_handle = quote! {
+        #runtime::ClientHandle
     };
 
-    quote! {
-        #[allow(clippy::type_complexity)]
-        pub mod #service_mod_name {
-            use crate::{
-                schema::{ToArgument, FromResponse},
-                error::RpcError,
-            };
+    Ok(quote! {
+        use #runtime::{
+            schema::ToArgument,
+            error::RpcError,
+        };
 
-            pub struct #q_service_name {
-                pub client: #arc_client,
-            }
+        pub struct #q_service_name {
+            pub client: #client_handle,
+            #(#cache_names: #cache_types,)*
+        }
 
-            impl #q_service_name {
-                pub fn new(client: #arc_client) -> Self {
-                    Self { client }
+        impl #q_service_name {
+            pub fn new(client: #client_handle) -> Self {
+                Self {
+                    client,
+                    #(#cache_names: Default::default(),)*
                 }
             }
 
-            #(#classes)*
-            #(#enums)*
-            #(#procedures)*
+            #invalidate
         }
+
+        #(#classes)*
+        #(#enums)*
+        #errors
+        #(#tuples)*
+        #(#procedures)*
+    })
+}
+
+fn generate_class_definitions(
+    json: &Value,
+    config: &BuildConfig,
+) -> Result<Vec<TokenStream>> {
+    let runtime = get_runtime_path(config)?;
+    Ok(get_object(json, "classes")?
+        .keys()
+        .map(|k| format_ident!("{}", k))
+        .map(|name| quote! {#runtime::schema::rpc_object!(#name);})
+        .collect())
+}
+
+/// Generate a struct for each tuple returned by a procedure,
+/// if enabled.
+fn generate_tuple_definitions(
+    json: &Value,
+    methods: &Methods,
+    service_name: &str,
+    config: &BuildConfig,
+) -> Result<Vec<TokenStream>> {
+    let procedures = get_object(json, "procedures")?;
+    let runtime = get_runtime_path(config)?;
+    let mut tuples = Vec::new();
+    for (name, definition) in procedures {
+        let in_procedure = |e| context(e, format_args!("procedure `{name}`"));
+        let Some(tuple) =
+            get_named_tuple(definition, config).map_err(in_procedure)?
+        else {
+            continue;
+        };
+        let (class_name, fn_name) = &methods[name.as_str()];
+        let struct_name = get_tuple_name(class_name, service_name, fn_name);
+        let owner = class_name
+            .as_ref()
+            .map_or_else(|| service_name.to_string(), Ident::to_string);
+        let doc = format!(" The result of [`{owner}::{fn_name}`].");
+
+        let types = get_array(tuple, "types").map_err(in_procedure)?;
+        let fields = types
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| {
+                let field = match types.len() {
+                    ..=4 => format_ident!("{}", ["x", "y", "z", "w"][index]),
+                    _ => format_ident!("item_{index}"),
+                };
+                let ty = decode_type(ty, false, false, config)?;
+                Ok(quote!(#field: #ty))
+            })
+            .collect::<Result<Vec<_>>>()
+            .map_err(in_procedure)?;
+        tuples.push(quote! {
+            #runtime::schema::rpc_tuple!(#[doc = #doc] #struct_name, [#(#fields),*]);
+        });
     }
+    Ok(tuples)
 }
 
-fn generate_class_definitions(json: &Value) -> TokenSet {
-    Box::new(
-        json.get("classes")
-            .unwrap()
-            .as_object()
-            .unwrap()
-            .keys()
-            .map(|k| format_ident!("{}", k))
-            .map(|name| quote! {crate::schema::rpc_object!(#name);}),
-    )
+/// The tuple type returned by a procedure, if it should be
+/// returned as a named struct instead.
+fn get_named_tuple<'a>(
+    definition: &'a Value,
+    config: &BuildConfig,
+) -> Result<Option<&'a Value>> {
+    let Some(return_value) = get_return_value(definition) else {
+        return Ok(None);
+    };
+    if !config.named_tuples
+        || get_str(return_value, "code")? != "TUPLE"
+        || get_type_override(return_value, config)?.is_some()
+    {
+        return Ok(None);
+    }
+    let nullable = get_bool(definition, "return_is_nullable")?.unwrap_or(false);
+    if !nullable
+        && get_geometry_type(return_value, definition, config)?.is_some()
+    {
+        return Ok(None);
+    }
+    Ok(Some(return_value))
 }
 
-fn generate_enum_definitions(json: &Value) -> TokenSet {
-    let enums = json.get("enumerations").unwrap().as_object().unwrap();
-    Box::new(enums.into_iter().map(|(name, values)| {
-        let name = format_ident!("{name}");
-        let variants = generate_enum_variant_definitions(values);
-        quote! {
-            crate::schema::rpc_enum!(#name, [#(#variants,)*]);
-        }
+/// The name of the struct returned in place of a tuple, after
+/// the class, or service, and method returning it.
+fn get_tuple_name(
+    class_name: &Option<Ident>,
+    service_name: &str,
+    fn_name: &str,
+) -> Ident {
+    let owner = class_name
+        .as_ref()
+        .map_or_else(|| service_name.to_string(), Ident::to_string);
+    format_ident!("{owner}{}", fn_name.to_case(Case::Pascal))
+}
+
+fn generate_enum_definitions(
+    json: &Value,
+    config: &BuildConfig,
+) -> Result<Vec<TokenStream>> {
+    let runtime = get_runtime_path(config)?;
+    get_object(json, "enumerations")?
+        .iter()
+        .map(|(name, values)| {
+            let variants =
+                generate_enum_variant_definitions(values).map_err(|e| {
+                    context(e, format_args!("enumeration `{name}`"))
+                })?;
+            let name = format_ident!("{name}");
+            Ok(quote! {
+                #runtime::schema::rpc_enum!(#name, [#(#variants,)*]);
+            })
+        })
+        .collect()
+}
+
+/// Generate an enum of the exceptions a service defines, if
+/// any, named after the service, e.g. `KRPCError`.
+fn generate_error_definition(
+    json: &Value,
+    service_name: &str,
+    config: &BuildConfig,
+) -> Result<Option<TokenStream>> {
+    let exceptions = match json.get("exceptions") {
+        Some(exceptions) => as_object(exceptions)?,
+        None => return Ok(None),
+    };
+    if exceptions.is_empty() {
+        return Ok(None);
+    }
+    let runtime = get_runtime_path(config)?;
+    let mut taken = HashMap::new();
+    let variants = exceptions
+        .iter()
+        .map(|(exception, definition)| {
+            // `InvalidOperationException` becomes `InvalidOperation`.
+            let variant = exception
+                .strip_suffix("Exception")
+                .filter(|name| !name.is_empty())
+                .unwrap_or(exception);
+            let variant = get_unique_variant(&mut taken, exception, variant)?;
+            let docs = get_docs(definition);
+            Ok(quote!(#(#[doc = #docs])* #variant = #exception))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let name = format_ident!("{service_name}Error");
+    Ok(Some(quote! {
+        #runtime::schema::rpc_error!(#name, #service_name, [#(#variants,)*]);
     }))
 }
 
-fn generate_enum_variant_definitions(json: &Value) -> TokenSet {
-    Box::new(
-        json.as_object()
-            .unwrap()
-            .get("values")
-            .unwrap()
-            .as_array()
-            .unwrap()
-            .iter()
-            .map(|value| {
-                format_ident!(
-                    "{}",
-                    value.get("name").unwrap().as_str().unwrap()
-                )
+fn generate_enum_variant_definitions(json: &Value) -> Result<Vec<TokenStream>> {
+    let mut taken = HashMap::new();
+    get_array(json, "values")?
+        .iter()
+        .map(|value| {
+            let name = get_str(value, "name")?;
+            let ident = get_unique_variant(&mut taken, name, name)?;
+            let discriminant = get(value, "value")?
+                .as_i64()
+                .and_then(|v| i32::try_from(v).ok())
+                .ok_or_else(|| invalid("`value` is not an i32"))?;
+            Ok(quote!(#ident = #discriminant))
+        })
+        .collect()
+}
+
+/// Turn an enumeration value name into a variant name.
+///
+/// Names already usable as Pascal case identifiers are kept as
+/// they are, so that acronyms such as `EVA` are not rewritten
+/// to `Eva`.
+///
+/// Others are converted to Pascal case, dropping any
+/// characters that can't appear in an identifier.
+fn get_variant_name(name: &str) -> String {
+    let is_pascal = |name: &str| {
+        name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    };
+
+    let mut variant = name.to_string();
+    if !is_pascal(&variant) {
+        variant = name
+            .to_case(Case::Pascal)
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .collect();
+    }
+    if !variant.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        variant.insert(0, '_');
+    }
+    rewrite_keywords(variant)
+}
+
+/// Turn `variant` into a variant name, as [`get_variant_name`]
+/// does, failing if an earlier variant of the same enum in
+/// `taken` already has it. `name` is the kRPC name it was
+/// taken from, for the error.
+fn get_unique_variant<'a>(
+    taken: &mut HashMap<String, &'a str>,
+    name: &'a str,
+    variant: &str,
+) -> Result<Ident> {
+    let variant = get_variant_name(variant);
+    if let Some(other) = taken.get(&variant) {
+        return Err(invalid(format!(
+            "`{other}` and `{name}` are both generated as `{variant}`"
+        )));
+    }
+    let ident = format_ident!("{variant}");
+    taken.insert(variant, name);
+    Ok(ident)
+}
+
+/// A service struct field holding the result of a cached or
+/// memoized procedure.
+struct CacheField {
+    name: Ident,
+    ty: TokenStream,
+    memoized: bool,
+}
+
+/// Generate a cache field for each cached or memoized
+/// procedure of a service.
+fn generate_cache_fields(
+    json: &Value,
+    methods: &Methods,
+    service_name: &str,
+    config: &BuildConfig,
+) -> Result<Vec<CacheField>> {
+    let procedures = get_object(json, "procedures")?;
+    procedures
+        .iter()
+        .filter(|(name, _)| {
+            is_cached(service_name, name, config)
+                || is_memoized(service_name, name, config)
+        })
+        .map(|(name, definition)| {
+            let (class_name, fn_name) = &methods[name.as_str()];
+            if class_name.is_some()
+                || !get_array(definition, "parameters")?.is_empty()
+            {
+                return Err(invalid(format!(
+                    "cached procedure `{name}` must belong to the service \
+                     and take no parameters"
+                )));
+            }
+            let memoized = is_memoized(service_name, name, config);
+            if memoized {
+                let returns_class = get_return_value(definition)
+                    .map(|ty| get_str(ty, "code"))
+                    .transpose()?
+                    == Some("CLASS");
+                if !returns_class || is_cached(service_name, name, config) {
+                    return Err(invalid(format!(
+                        "memoized procedure `{name}` must return a class, \
+                         and not also be cached"
+                    )));
+                }
+            }
+            let tuple_name = get_tuple_name(class_name, service_name, fn_name);
+            let ret = get_return_type(definition, &tuple_name, config)?;
+            Ok(CacheField {
+                name: get_cache_name(fn_name),
+                ty: match memoized {
+                    true => quote!(::std::sync::Mutex<Option<#ret>>),
+                    false => quote!(::std::sync::OnceLock<#ret>),
+                },
+                memoized,
             })
-            .map(|ident| quote!(#ident)),
-    )
+        })
+        .collect()
 }
 
-fn generate_procedure_definitions<'a>(
-    json: &'a Value,
-    service_name: &'a str,
-    q_service_name: &'a Ident,
-) -> TokenSet<'a> {
-    Box::new(
-        json.get("procedures")
-            .unwrap()
-            .as_object()
-            .unwrap()
-            .iter()
-            .map(|(name, definition)| {
-                generate_procedure_definition(
-                    name,
-                    definition,
-                    service_name,
-                    q_service_name,
-                )
-                .to_token_stream()
-            }),
-    )
+fn is_cached(service_name: &str, name: &str, config: &BuildConfig) -> bool {
+    config
+        .cached_procedures
+        .contains(&format!("{service_name}.{name}"))
+}
+
+fn is_memoized(service_name: &str, name: &str, config: &BuildConfig) -> bool {
+    config
+        .memoized_procedures
+        .contains(&format!("{service_name}.{name}"))
+}
+
+fn get_cache_name(fn_name: &str) -> Ident {
+    format_ident!("{fn_name}_cache")
+}
+
+fn generate_procedure_definitions(
+    json: &Value,
+    methods: &Methods,
+    service_name: &str,
+    q_service_name: &Ident,
+    config: &BuildConfig,
+) -> Result<Vec<TokenStream>> {
+    let procedures = get_object(json, "procedures")?;
+    procedures
+        .iter()
+        .map(|(name, definition)| {
+            generate_procedure_definition(
+                name,
+                &methods[name.as_str()],
+                definition,
+                service_name,
+                q_service_name,
+                config,
+            )
+            .map_err(|e| context(e, format_args!("procedure `{name}`")))
+        })
+        .collect()
 }
 
 fn generate_procedure_definition(
     name: &str,
+    (class_name, fn_name): &(Option<Ident>, String),
     definition: &Value,
     service_name: &str,
     q_service_name: &Ident,
-) -> impl ToTokens {
-    let name_tokens = name.split('_').collect::<Vec<&str>>();
-    let class_name = get_struct(&name_tokens);
-
-    let fn_name = get_fn_name(&name_tokens, &class_name);
-    let q_class_name = class_name.unwrap_or_else(|| q_service_name.clone());
+    config: &BuildConfig,
+) -> Result<TokenStream> {
+    let q_class_name =
+        class_name.clone().unwrap_or_else(|| q_service_name.clone());
 
     let Parameters {
         names,
         types,
         as_args,
-    } = Parameters::from_json(definition);
+        has_defaults,
+    } = Parameters::from_json(definition, config)?;
+
+    // Arguments left as `None` are omitted so that the server
+    // falls back to their default values.
+    let args = if has_defaults {
+        quote!(vec![#(#as_args),*].into_iter().flatten().collect())
+    } else {
+        quote!(vec![#(#as_args),*])
+    };
 
     let call_name = format_ident!("{fn_name}_call");
     let stream_name = format_ident!("{fn_name}_stream");
-    let fn_name = format_ident!("{fn_name}");
-    let ret = get_return_type(definition);
-    quote! {
+    let cache_name = get_cache_name(fn_name);
+    let tuple_name = get_tuple_name(class_name, service_name, fn_name);
+    let fn_name = format_ident!("{}", rewrite_keywords(fn_name.clone()));
+    let ret = get_return_type(definition, &tuple_name, config)?;
+    let docs = get_docs(definition);
+    let runtime = get_runtime_path(config)?;
+    let body = if is_cached(service_name, name, config) {
+        quote! {
+            if let Some(value) = self.#cache_name.get() {
+                return Ok(Clone::clone(value));
+            }
+            let value: #ret = self.client.invoke(
+                #runtime::schema::Request::from(self.#call_name(#(#names),*)?))?;
+
+            Ok(Clone::clone(self.#cache_name.get_or_init(|| value)))
+        }
+    } else if is_memoized(service_name, name, config) {
+        // Holding the lock while fetching keeps concurrent
+        // callers from each fetching the object. A poisoned
+        // cache holds at worst nothing, so is recovered, as in
+        // `invalidate`.
+        quote! {
+            let mut cache = self.#cache_name
+                .lock()
+                .unwrap_or_else(::std::sync::PoisonError::into_inner);
+            if let Some(value) = cache.as_ref() {
+                return Ok(Clone::clone(value));
+            }
+            let value: #ret = self.client.invoke(
+                #runtime::schema::Request::from(self.#call_name(#(#names),*)?))?;
+
+            Ok(Clone::clone(cache.insert(value)))
+        }
+    } else {
+        quote! {
+            self.client.invoke(
+                #runtime::schema::Request::from(self.#call_name(#(#names),*)?))
+        }
+    };
+    // Results are already `must_use`, but not the value inside.
+    let must_use = get_return_value(definition).map(|_| quote!(#[must_use]));
+    Ok(quote! {
         impl #q_class_name {
-            pub(crate) fn #call_name(
+            pub fn #call_name(
                 &self, #(#names: #types),*
-            ) -> Result<crate::schema::ProcedureCall, RpcError> {
-                Ok(crate::client::Client::proc_call(
+            ) -> Result<#runtime::schema::ProcedureCall, RpcError> {
+                Ok(#runtime::client::Client::proc_call(
                     #service_name,
                     #name,
-                    vec![#(#as_args),*]
+                    #args
                 ))
             }
 
             pub fn #stream_name(
                 &self, #(#names: #types),*
-            ) -> Result<crate::stream::Stream<#ret>, RpcError> {
-                crate::stream::Stream::new(
-                    self.client.clone(),
-                    self.#call_name(#(#names),*)?
-                )
+            ) -> Result<#runtime::stream::Stream<#ret>, RpcError> {
+                self.client.add_stream(self.#call_name(#(#names),*)?)
             }
 
+            #(#[doc = #docs])*
+            #must_use
             pub fn #fn_name(
                 &self, #(#names: #types),*
             ) -> Result<#ret, RpcError> {
-                let request = crate::schema::Request::from(
-                    self.#call_name(#(#names),*)?);
-                let response = self.client.call(request)?;
-
-                <#ret>::from_response(response, self.client.clone())
+                #body
             }
         }
-    }
+    })
 }
 
 fn get_struct(proc_tokens: &Vec<&str>) -> Option<Ident> {
@@ -253,77 +1214,231 @@ struct Parameters {
     names: Vec<Ident>,
     as_args: Vec<TokenStream>,
     types: Vec<TokenStream>,
+    has_defaults: bool,
 }
 
 impl Parameters {
-    fn from_json(json: &Value) -> Self {
+    fn from_json(json: &Value, config: &BuildConfig) -> Result<Self> {
         let mut names = Vec::new();
         let mut types = Vec::new();
         let mut args = Vec::new();
+        let mut optional_args = Vec::new();
 
-        let params = json
-            .as_object()
-            .unwrap()
-            .get("parameters")
-            .unwrap()
-            .as_array()
-            .unwrap();
-
-        for (pos, param_json) in params.iter().enumerate() {
-            let param = param_json.as_object().unwrap();
-            let name: String = rewrite_keywords(
-                param
-                    .get("name")
-                    .unwrap()
-                    .as_str()
-                    .unwrap()
-                    .to_case(Case::Snake),
-            );
+        let params = get_array(json, "parameters")?;
+
+        let mut positions = HashSet::new();
+        for (index, param) in params.iter().enumerate() {
+            let name: String =
+                rewrite_keywords(get_str(param, "name")?.to_case(Case::Snake));
+            let pos = get_position(param, index)
+                .map_err(|e| context(e, format_args!("parameter `{name}`")))?;
+            if !positions.insert(pos) {
+                return Err(invalid(format!(
+                    "parameter `{name}` reuses position {pos}"
+                )));
+            }
+            let has_default = param.get("default_value").is_some();
             if name.eq_ignore_ascii_case("this") {
-                args.push(quote! { self.to_argument(#pos as u32)? });
+                args.push(quote! { self.to_argument(#pos)? });
             } else {
+                // Defaulted parameters are optional. Those that are
+                // also nullable always default to null, so a single
+                // `Option` covers both.
+                let nullable = get_bool(param, "nullable")?.unwrap_or(false);
+                types.push(
+                    decode_type(
+                        get(param, "type")?,
+                        true,
+                        nullable || has_default,
+                        config,
+                    )
+                    .map_err(|e| {
+                        context(e, format_args!("parameter `{name}`"))
+                    })?,
+                );
+
                 let name = format_ident!("{name}");
-                args.push(quote!(#name.to_argument(#pos as u32)?));
+                if has_default {
+                    optional_args.push(quote! {
+                        #name.map(|v| v.to_argument(#pos)).transpose()?
+                    });
+                } else {
+                    args.push(quote!(#name.to_argument(#pos)?));
+                }
                 names.push(name);
-
-                let nullable = param
-                    .get("nullable")
-                    .map(|b| b.as_bool().unwrap())
-                    .unwrap_or(false);
-                types.push(decode_type(
-                    param.get("type").unwrap().as_object().unwrap(),
-                    true,
-                    nullable,
-                ));
             }
         }
-        Self {
+
+        let has_defaults = !optional_args.is_empty();
+        if has_defaults {
+            args = args
+                .into_iter()
+                .map(|arg| quote!(Some(#arg)))
+                .chain(optional_args)
+                .collect();
+        }
+
+        Ok(Self {
             names,
             types,
             as_args: args,
+            has_defaults,
+        })
+    }
+}
+
+/// The method of each of a service's procedures, keyed by
+/// procedure name, as the class it belongs to, if any, and the
+/// method's name.
+type Methods<'a> = HashMap<&'a str, (Option<Ident>, String)>;
+
+/// Name the method for each of a service's procedures.
+///
+/// Distinct procedures can map to the same method, such as
+/// `get_Foo` and `Foo`. Property getters take the name first,
+/// as its most natural owners, then other procedures in order.
+/// Each after the first to a name has an index appended, e.g.
+/// `foo_2`, with a warning.
+fn get_methods(procedures: &serde_json::Map<String, Value>) -> Methods<'_> {
+    let mut names = procedures.keys().collect::<Vec<_>>();
+    names.sort_by_key(|name| !is_getter(name));
+
+    let mut taken = HashSet::new();
+    names
+        .into_iter()
+        .map(|name| {
+            let (class_name, fn_name) = get_method(name);
+            let class = class_name.as_ref().map(Ident::to_string);
+            let mut unique = fn_name.clone();
+            for index in 2.. {
+                if taken.insert((class.clone(), unique.clone())) {
+                    break;
+                }
+                unique = format!("{fn_name}_{index}");
+            }
+            if unique != fn_name {
+                println!(
+                    "cargo:warning=procedure `{name}` generated as \
+                     `{unique}`, as `{fn_name}` is taken"
+                );
+            }
+            (name.as_str(), (class_name, unique))
+        })
+        .collect()
+}
+
+/// Whether a procedure is a property getter, of the service or
+/// of a class, e.g. `get_Foo` or `Class_get_Foo`.
+fn is_getter(name: &str) -> bool {
+    let tokens = name.split('_').collect::<Vec<_>>();
+    matches!(
+        tokens.as_slice(),
+        ["get", property @ ..] | [_, "get", property @ ..]
+            if !property.is_empty()
+    )
+}
+
+/// Split a procedure name into the class it is a method of,
+/// if any, and the method's name.
+///
+/// Static class procedures need no instance, so they belong to
+/// the service, named after their class: `Class_static_Name`
+/// becomes `class_name`.
+fn get_method(name: &str) -> (Option<Ident>, String) {
+    let name_tokens = name.split('_').collect::<Vec<&str>>();
+    match name_tokens.as_slice() {
+        [class, "static", rest @ ..] if !rest.is_empty() => {
+            let tokens = [&[*class], rest].concat();
+            (None, get_fn_name(&tokens, &None::<Ident>))
+        }
+        _ => {
+            let class_name = get_struct(&name_tokens);
+            let fn_name = get_fn_name(&name_tokens, &class_name);
+            (class_name, fn_name)
         }
     }
 }
 
 fn get_fn_name<T>(proc_tokens: &[&str], class: &Option<T>) -> String {
-    match class {
+    let tokens = match class {
         Some(_) => &proc_tokens[1..],
         None => proc_tokens,
-    }
-    .join("_")
-    .to_case(Case::Snake)
+    };
+    // Property getters are named after the property itself.
+    let tokens = match tokens {
+        ["get", property @ ..] if !property.is_empty() => property,
+        _ => tokens,
+    };
+    tokens.join("_").to_case(Case::Snake)
 }
 
 fn decode_type(
-    ty: &serde_json::Map<String, Value>,
+    ty: &Value,
     borrow: bool,
     nullable: bool,
-) -> TokenStream {
-    let code = ty.get("code").unwrap().as_str().unwrap();
+    config: &BuildConfig,
+) -> Result<TokenStream> {
+    let code = get_str(ty, "code")?;
+
+    let overridden = get_type_override(ty, config)?;
+    let builtin = overridden.is_none();
+    let mut type_stream = match overridden {
+        Some(type_stream) => type_stream,
+        None => decode_builtin_type(code, ty, config)?,
+    };
+
+    if borrow {
+        type_stream = match code {
+            "CLASS" => quote!(&#type_stream),
+            // Parameters take string slices, so that literals
+            // can be passed as they are.
+            "STRING" if builtin => quote!(&str),
+            _ => type_stream,
+        }
+    };
+
+    if nullable {
+        type_stream = quote!(Option<#type_stream>)
+    }
+
+    Ok(type_stream)
+}
 
-    let mut type_stream = match code {
+fn get_type_override(
+    ty: &Value,
+    config: &BuildConfig,
+) -> Result<Option<TokenStream>> {
+    let class_name = ty
+        .get("service")
+        .and_then(Value::as_str)
+        .zip(ty.get("name").and_then(Value::as_str))
+        .map(|(service, name)| format!("{service}.{name}"));
+    let code = get_str(ty, "code")?;
+
+    class_name
+        .and_then(|name| config.type_overrides.get(&name))
+        .or_else(|| config.type_overrides.get(code))
+        .map(|rust_type| {
+            syn::parse_str::<syn::Type>(rust_type)
+                .map(ToTokens::into_token_stream)
+                .map_err(|e| {
+                    invalid(format!("invalid type override `{rust_type}`: {e}"))
+                })
+        })
+        .transpose()
+}
+
+fn decode_builtin_type(
+    code: &str,
+    ty: &Value,
+    config: &BuildConfig,
+) -> Result<TokenStream> {
+    let runtime = get_runtime_path(config)?;
+    Ok(match code {
+        "NONE" => quote!(()),
         "STRING" => quote!(String),
         "SINT32" => quote!(i32),
+        "SINT64" => quote!(i64),
         "UINT32" => quote!(u32),
         "UINT64" => quote!(u64),
         "BOOL" => quote!(bool),
@@ -331,104 +1446,300 @@ fn decode_type(
         "DOUBLE" => quote!(f64),
         // TODO(kladd): maybe not Vec<u8>
         "BYTES" => quote!(Vec<u8>),
-        "TUPLE" => decode_tuple(ty),
-        "LIST" => decode_list(ty),
-        "SET" => decode_set(ty),
-        "DICTIONARY" => decode_dictionary(ty),
-        "ENUMERATION" => decode_class(ty),
-        "CLASS" => decode_class(ty),
-        "EVENT" => quote!(crate::schema::Event),
-        "PROCEDURE_CALL" => quote!(crate::schema::ProcedureCall),
-        "STREAM" => quote!(crate::schema::Stream),
-        "SERVICES" => quote!(crate::schema::Services),
-        "STATUS" => quote!(crate::schema::Status),
-        _ => quote!(),
-    };
+        "TUPLE" => decode_tuple(ty, config)?,
+        "LIST" => decode_list(ty, config)?,
+        "SET" => decode_set(ty, config)?,
+        "DICTIONARY" => decode_dictionary(ty, config)?,
+        "ENUMERATION" => decode_class(ty)?,
+        "CLASS" => decode_class(ty)?,
+        "EVENT" => quote!(#runtime::stream::Event),
+        "PROCEDURE_CALL" => quote!(#runtime::schema::ProcedureCall),
+        "STREAM" => quote!(#runtime::schema::Stream),
+        "SERVICES" => quote!(#runtime::schema::Services),
+        "STATUS" => quote!(#runtime::schema::Status),
+        _ => return Err(invalid(format!("unsupported type code `{code}`"))),
+    })
+}
 
-    if borrow {
-        type_stream = match code {
-            "CLASS" => quote!(&#type_stream),
-            _ => type_stream,
+/// Extract the `<summary>` of a definition's `documentation`
+/// as lines of Markdown, one per `#[doc]` attribute.
+fn get_docs(definition: &Value) -> Vec<String> {
+    let summary = definition
+        .get("documentation")
+        .and_then(Value::as_str)
+        .and_then(|doc| doc.split_once("<summary>"))
+        .and_then(|(_, rest)| rest.split_once("</summary>"))
+        .map(|(summary, _)| summary)
+        .unwrap_or_default();
+
+    let mut lines: Vec<String> = Vec::new();
+    for line in strip_doc_tags(summary).lines().map(str::trim) {
+        // Keep paragraph breaks, but only one at a time.
+        if !line.is_empty() || lines.last().is_some_and(|l| !l.is_empty()) {
+            lines.push(format!(" {line}").trim_end().into());
         }
-    };
+    }
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
 
-    if nullable {
-        type_stream = quote!(Option<#type_stream>)
+/// Rewrite the XML markup used in kRPC documentation.
+/// References and inline code become code spans, and any
+/// other tags are dropped in favor of their contents.
+fn strip_doc_tags(doc: &str) -> String {
+    let mut out = String::new();
+    let mut rest = doc;
+    let mut in_item = false;
+    while let Some((text, tail)) = rest.split_once('<') {
+        if in_item {
+            // List items must stay on one line to render.
+            out.push_str(
+                &text.split_whitespace().collect::<Vec<_>>().join(" "),
+            );
+        } else {
+            out.push_str(text);
+        }
+        let Some((tag, tail)) = tail.split_once('>') else {
+            // Not a tag, such as a comparison.
+            out.push('<');
+            rest = tail;
+            break;
+        };
+        rest = tail;
+
+        let attr = tag.split('"').nth(1);
+        if tag.starts_with("see ") {
+            let target = attr.unwrap_or_default();
+            let target = target.split_once(':').map_or(target, |(_, t)| t);
+            out.push_str(&format!("`{target}`"));
+        } else if tag.starts_with("paramref ") {
+            let param = attr.unwrap_or_default().to_case(Case::Snake);
+            out.push_str(&format!("`{param}`"));
+        } else if tag == "c" || tag == "/c" {
+            out.push('`');
+        } else if tag == "item" {
+            in_item = true;
+            out.push_str("\n* ");
+        } else if tag == "/item" {
+            in_item = false;
+        } else if tag == "/list" {
+            out.push_str("\n\n");
+        }
     }
+    out.push_str(rest);
+    out
+}
 
-    type_stream
+/// The type a procedure returns, if any. Procedures without a
+/// result may also have a return type of code `NONE`.
+fn get_return_value(definition: &Value) -> Option<&Value> {
+    definition
+        .get("return_type")
+        .filter(|ty| ty.get("code").and_then(Value::as_str) != Some("NONE"))
 }
 
-fn get_return_type(definition: &Value) -> TokenStream {
+/// The type a procedure returns, where `tuple_name` names the
+/// struct returned in place of a tuple, if enabled.
+fn get_return_type(
+    definition: &Value,
+    tuple_name: &Ident,
+    config: &BuildConfig,
+) -> Result<TokenStream> {
     let mut ret = quote!(());
-    if let Some(return_value) = definition.get("return_type") {
-        let nullable = definition
-            .get("return_is_nullable")
-            .map(|b| b.as_bool().unwrap())
-            .unwrap_or(false);
-        let ty = return_value.as_object().unwrap();
-        ret = decode_type(ty, false, nullable);
+    if let Some(return_value) = get_return_value(definition) {
+        let nullable =
+            get_bool(definition, "return_is_nullable")?.unwrap_or(false);
+        if get_named_tuple(definition, config)?.is_some() {
+            return Ok(match nullable {
+                true => quote!(Option<#tuple_name>),
+                false => quote!(#tuple_name),
+            });
+        }
+        ret = match get_geometry_type(return_value, definition, config)? {
+            Some(geometry_type) if !nullable => geometry_type,
+            _ => decode_type(return_value, false, nullable, config)
+                .map_err(|e| context(e, "return type"))?,
+        };
     }
-    ret
+    Ok(ret)
 }
 
-fn decode_tuple(ty: &serde_json::Map<String, Value>) -> TokenStream {
-    let types = ty
-        .get("types")
-        .unwrap()
-        .as_array()
-        .unwrap()
+/// Choose a geometry type for a tuple of doubles, if enabled
+/// and the procedure's documentation describes the tuple as a
+/// vector or quaternion.
+fn get_geometry_type(
+    ty: &Value,
+    definition: &Value,
+    config: &BuildConfig,
+) -> Result<Option<TokenStream>> {
+    if !config.geometry_types
+        || get_str(ty, "code")? != "TUPLE"
+        || get_type_override(ty, config)?.is_some()
+    {
+        return Ok(None);
+    }
+    let types = get_array(ty, "types")?;
+    if !types
         .iter()
-        .map(|ty| decode_type(ty.as_object().unwrap(), false, false));
-    quote! {(#(#types),*)}
+        .all(|ty| get_str(ty, "code").is_ok_and(|c| c == "DOUBLE"))
+    {
+        return Ok(None);
+    }
+
+    let docs = definition
+        .get("documentation")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_lowercase();
+    let runtime = get_runtime_path(config)?;
+    Ok(match types.len() {
+        3 if docs.contains("vector") => Some(quote!(#runtime::Vector3)),
+        4 if docs.contains("quaternion") => Some(quote!(#runtime::Quaternion)),
+        _ => None,
+    })
 }
 
-fn decode_list(ty: &serde_json::Map<String, Value>) -> TokenStream {
-    let types = ty.get("types").unwrap().as_array().unwrap();
-    let ty =
-        decode_type(types.first().unwrap().as_object().unwrap(), false, false);
-    quote!( Vec<#ty> )
+/// The largest tuple the client can encode and decode.
+const MAX_TUPLE_ARITY: usize = 5;
+
+fn decode_tuple(ty: &Value, config: &BuildConfig) -> Result<TokenStream> {
+    let types = get_array(ty, "types")?;
+    if !(2..=MAX_TUPLE_ARITY).contains(&types.len()) {
+        return Err(invalid(format!(
+            "tuples of {} elements are unsupported, only 2 to \
+             {MAX_TUPLE_ARITY}",
+            types.len()
+        )));
+    }
+    let types = types
+        .iter()
+        .map(|ty| decode_type(ty, false, false, config))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(quote! {(#(#types),*)})
 }
 
-fn decode_class(ty: &serde_json::Map<String, Value>) -> TokenStream {
-    let service = format_ident!(
-        "{}",
-        ty.get("service")
-            .unwrap()
-            .as_str()
-            .unwrap()
-            .to_case(Case::Snake)
-    );
-    let name = format_ident!("{}", ty.get("name").unwrap().as_str().unwrap());
-
-    quote!(
-        crate::services::#service::#name
-    )
+fn decode_list(ty: &Value, config: &BuildConfig) -> Result<TokenStream> {
+    let ty = decode_type(get_type_argument(ty, 0)?, false, false, config)?;
+    Ok(quote!( Vec<#ty> ))
 }
 
-fn decode_dictionary(ty: &serde_json::Map<String, Value>) -> TokenStream {
-    let types = ty.get("types").unwrap().as_array().unwrap();
+// Service modules are siblings, wherever they are generated.
+fn decode_class(ty: &Value) -> Result<TokenStream> {
+    let service =
+        format_ident!("{}", get_str(ty, "service")?.to_case(Case::Snake));
+    let name = format_ident!("{}", get_str(ty, "name")?);
 
+    Ok(quote!(
+        super::#service::#name
+    ))
+}
+
+fn decode_dictionary(ty: &Value, config: &BuildConfig) -> Result<TokenStream> {
     let key_name =
-        decode_type(types.get(0).unwrap().as_object().unwrap(), false, false);
+        decode_type(get_type_argument(ty, 0)?, false, false, config)?;
     let value_name =
-        decode_type(types.get(1).unwrap().as_object().unwrap(), false, false);
+        decode_type(get_type_argument(ty, 1)?, false, false, config)?;
 
-    quote!(std::collections::HashMap<#key_name, #value_name>)
+    Ok(quote!(::std::collections::HashMap<#key_name, #value_name>))
 }
 
-fn decode_set(ty: &serde_json::Map<String, Value>) -> TokenStream {
-    let types = ty.get("types").unwrap().as_array().unwrap();
-    let ty =
-        decode_type(types.first().unwrap().as_object().unwrap(), false, false);
-    quote!(
-        std::collections::HashSet<#ty>
-    )
+fn decode_set(ty: &Value, config: &BuildConfig) -> Result<TokenStream> {
+    let ty = decode_type(get_type_argument(ty, 0)?, false, false, config)?;
+    Ok(quote!(::std::collections::HashSet<#ty>))
+}
+
+fn get_type_argument(ty: &Value, index: usize) -> Result<&Value> {
+    get_array(ty, "types")?
+        .get(index)
+        .ok_or_else(|| invalid(format!("missing type argument {index}")))
+}
+
+fn get<'a>(json: &'a Value, key: &str) -> Result<&'a Value> {
+    json.get(key)
+        .ok_or_else(|| invalid(format!("missing key `{key}`")))
+}
+
+fn get_object<'a>(
+    json: &'a Value,
+    key: &str,
+) -> Result<&'a serde_json::Map<String, Value>> {
+    as_object(get(json, key)?).map_err(|e| context(e, format_args!("`{key}`")))
+}
+
+fn get_array<'a>(json: &'a Value, key: &str) -> Result<&'a Vec<Value>> {
+    get(json, key)?
+        .as_array()
+        .ok_or_else(|| invalid(format!("`{key}` is not an array")))
+}
+
+fn get_str<'a>(json: &'a Value, key: &str) -> Result<&'a str> {
+    get(json, key)?
+        .as_str()
+        .ok_or_else(|| invalid(format!("`{key}` is not a string")))
+}
+
+/// The position the server expects a parameter's argument at.
+/// kRPC numbers parameters in order, but a definition may give
+/// positions explicitly, which need not be contiguous.
+fn get_position(param: &Value, index: usize) -> Result<u32> {
+    match param.get("position") {
+        Some(position) => position
+            .as_u64()
+            .and_then(|position| u32::try_from(position).ok())
+            .ok_or_else(|| invalid("`position` is not a u32")),
+        None => u32::try_from(index).map_err(invalid),
+    }
+}
+
+/// Optional boolean fields are absent rather than `false`.
+fn get_bool(json: &Value, key: &str) -> Result<Option<bool>> {
+    json.get(key)
+        .map(|b| {
+            b.as_bool()
+                .ok_or_else(|| invalid(format!("`{key}` is not a boolean")))
+        })
+        .transpose()
+}
+
+fn as_object(json: &Value) -> Result<&serde_json::Map<String, Value>> {
+    json.as_object()
+        .ok_or_else(|| invalid("expected an object"))
+}
+
+/// The path to the client's runtime, as configured.
+fn get_runtime_path(config: &BuildConfig) -> Result<TokenStream> {
+    let path = config.runtime_path.as_deref().unwrap_or("crate");
+    let path = syn::parse_str::<syn::Path>(path)
+        .map_err(|e| invalid(format!("invalid runtime path `{path}`: {e}")))?;
+    Ok(path.to_token_stream())
+}
+
+fn invalid(
+    error: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// Prefix an error with where in the definitions it occurred.
+fn context(error: io::Error, at: impl std::fmt::Display) -> io::Error {
+    io::Error::new(error.kind(), format!("{at}: {error}"))
 }
 
+/// Escape identifiers that collide with Rust keywords.
 fn rewrite_keywords(sample: String) -> String {
     match sample.as_str() {
-        "type" => "r#type".into(),
+        // These can't be raw identifiers.
+        "crate" | "self" | "super" | "Self" => format!("{sample}_"),
+        "abstract" | "as" | "async" | "await" | "become" | "box" | "break"
+        | "const" | "continue" | "do" | "dyn" | "else" | "enum" | "extern"
+        | "false" | "final" | "fn" | "for" | "gen" | "if" | "impl" | "in"
+        | "let" | "loop" | "macro" | "match" | "mod" | "move" | "mut"
+        | "override" | "priv" | "pub" | "ref" | "return" | "static"
+        | "struct" | "trait" | "true" | "try" | "type" | "typeof"
+        | "unsafe" | "unsized" | "use" | "virtual" | "where" | "while"
+        | "yield" => format!("r#{sample}"),
         _ => sample,
     }
 }
@@ -0,0 +1,102 @@
+use std::marker::PhantomData;
+
+use crate::{
+    client::{call_response, ClientHandle},
+    error::RpcError,
+    schema::{FromResponse, ProcedureCall, ProcedureResult},
+    RpcType,
+};
+
+/// A set of procedure calls sent to the server in a single
+/// request.
+///
+/// Calls are added with [`add`][add], using the `_call()`
+/// variant of any procedure, and return a handle for reading
+/// that call's result once the batch has been sent with
+/// [`call`][call].
+///
+#[cfg_attr(feature = "space_center", doc = "```no_run")]
+#[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
+/// use krpc_client::{
+///     batch::Batch, services::space_center::SpaceCenter, Client,
+/// };
+/// # let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
+/// let space_center = SpaceCenter::new(client.clone());
+///
+/// let mut batch = Batch::new(client);
+/// let ut = batch.add::<f64>(space_center.ut_call()?);
+/// let g = batch.add::<f64>(space_center.g_call()?);
+///
+/// let results = batch.call()?;
+/// println!("{} {}", results.get(&ut)?, results.get(&g)?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [add]: Batch::add
+/// [call]: Batch::call
+pub struct Batch {
+    client: ClientHandle,
+    calls: Vec<ProcedureCall>,
+}
+
+/// A handle to the result of a call added to a [`Batch`].
+pub struct BatchCall<T: RpcType> {
+    index: usize,
+    phantom: PhantomData<T>,
+}
+
+/// The results of a [`Batch`], read with the handles returned
+/// when adding its calls.
+///
+/// Each call succeeds or fails on its own, so one failing
+/// doesn't lose the results of the rest.
+pub struct BatchResults {
+    client: ClientHandle,
+    calls: Vec<ProcedureCall>,
+    results: Vec<ProcedureResult>,
+}
+
+impl Batch {
+    /// Constructs a new, empty `Batch`.
+    pub fn new(client: ClientHandle) -> Self {
+        Self {
+            client,
+            calls: Vec::new(),
+        }
+    }
+
+    /// Add a call to the batch. `T` must be the return type of
+    /// the procedure being called.
+    pub fn add<T: RpcType>(&mut self, call: ProcedureCall) -> BatchCall<T> {
+        self.calls.push(call);
+        BatchCall {
+            index: self.calls.len() - 1,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Send every call in the batch to the server in one
+    /// request. Fails only if the request as a whole does,
+    /// such as when the connection is lost.
+    pub fn call(self) -> Result<BatchResults, RpcError> {
+        Ok(BatchResults {
+            results: self.client.call_batch(&self.calls)?,
+            calls: self.calls,
+            client: self.client,
+        })
+    }
+}
+
+impl BatchResults {
+    /// Decode the result of a call from this batch, or return
+    /// the error it failed with.
+    pub fn get<T: RpcType>(&self, call: &BatchCall<T>) -> Result<T, RpcError> {
+        let result = self.results.get(call.index).ok_or_else(|| {
+            RpcError::Encoding("batch result out of range".into())
+        })?;
+        let response = call_response(&self.calls[call.index], result)?;
+        T::from_response(response, self.client.clone())
+    }
+}
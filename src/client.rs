@@ -1,20 +1,55 @@
 use std::{
-    net::TcpStream,
-    sync::{Arc, Mutex},
+    io::{self, Read, Write},
+    net::{Shutdown, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
-use protobuf::CodedInputStream;
+use protobuf::Message;
 
 use crate::{
-    error::RpcError,
+    error::{RemoteError, RpcError},
+    prepared::PreparedCall,
     schema::{
         self, connection_request, connection_response::Status,
-        ConnectionRequest, ConnectionResponse, DecodeUntagged, StreamUpdate,
+        ConnectionRequest, ConnectionResponse, DecodeUntagged, FromResponse,
+        StreamUpdate,
+    },
+    services::{
+        krpc::{GameScene, KRPC},
+        KRPC_VERSION,
     },
-    stream::StreamWrangler,
+    stream::{Stream, StreamKey, StreamWrangler},
+    transport::{RpcTransport, StreamSink},
+    RpcType,
 };
 
+/// The default limit on the size of a message from the server,
+/// in bytes.
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// The handle to a [`Client`] that services, streams and
+/// objects share.
+///
+/// This is an `Arc`, or with the `rc` feature an `Rc`, for
+/// single-threaded programs that would rather not pay for
+/// atomic reference counts. Such a client can't be sent to
+/// another thread, so [`ClientBuilder`] then has no
+/// keepalive option.
+#[cfg(not(feature = "rc"))]
+pub type ClientHandle = Arc<Client>;
+#[cfg(feature = "rc")]
+pub type ClientHandle = std::rc::Rc<Client>;
+
+#[cfg(not(feature = "rc"))]
+type WeakHandle = std::sync::Weak<Client>;
+#[cfg(feature = "rc")]
+type WeakHandle = std::rc::Weak<Client>;
+
 /// The base kRPC client type.
 ///
 /// ## Connecting to the kRPC server
@@ -33,24 +68,459 @@ use crate::{
 /// [`Client::new`][new] to any RPC service in
 /// [`krpc_client::services::*`][services].
 ///
-/// ```
+#[cfg_attr(feature = "space_center", doc = "```no_run")]
+#[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
 /// use krpc_client::{services::space_center::SpaceCenter, Client};
+/// # let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
 /// let space_center = SpaceCenter::new(client);
 /// // Then call procedures with the created service.
-/// println!("Hello, {}!", space_center.get_active_vessel()?.get_name()?);
+/// println!("Hello, {}!", space_center.active_vessel()?.name()?);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ## Threads
+///
+/// A client may be shared between threads. Calls from
+/// different threads are made one at a time, each request
+/// and its response exchanged under a lock on the RPC
+/// connection, so they can't interleave. Batch calls to
+/// make several in one exchange.
+///
+/// ## Reconnecting
+///
+/// By default a lost connection is reported as
+/// [`RpcError::Connection`]. Set a [`ReconnectPolicy`] to
+/// have the client re-establish its connections instead;
+/// see [`set_reconnect_policy`][reconnect].
+///
+/// ## Testing without a server
+///
+/// [`Client::with_transport`] makes calls through any
+/// [`RpcTransport`][transport], such as a
+/// [`MockClient`][mock].
+///
+/// ## Several servers
+///
+/// Clients share no state, so one process may connect to
+/// any number of servers. Stream IDs are only unique per
+/// server, and each client keeps its own streams.
+///
+#[cfg_attr(feature = "space_center", doc = "```")]
+#[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
+/// use krpc_client::{
+///     services::space_center::SpaceCenter, transport::MockClient,
+/// };
+/// let (first, second) = (MockClient::new(), MockClient::new());
+/// first.respond("SpaceCenter", "get_UT", 1.0)?;
+/// second.respond("SpaceCenter", "get_UT", 2.0)?;
+///
+/// let first_ut = SpaceCenter::new(first.client()).ut_stream()?;
+/// let second_ut = SpaceCenter::new(second.client()).ut_stream()?;
+///
+/// first.respond("SpaceCenter", "get_UT", 10.0)?;
+/// first.update_streams()?;
+/// assert_eq!(first_ut.get()?, 10.0);
+/// assert_eq!(second_ut.get()?, 2.0);
+/// # Ok(())
+/// # }
 /// ```
 ///
 /// [new]: Client::new
 /// [services]: crate::services
+/// [reconnect]: Client::set_reconnect_policy
+/// [transport]: crate::transport::RpcTransport
+/// [mock]: crate::transport::MockClient
 pub struct Client {
+    transport: Transport,
+    streams: Arc<StreamWrangler>,
+    stats: Counters,
+    on_reconnect: Mutex<Option<ReconnectHook>>,
+    on_call: Mutex<Option<CallHook>>,
+    /// Whether `on_call` is set, so that calls without a hook
+    /// needn't take its lock.
+    has_on_call: AtomicBool,
+    retry: Mutex<RetryPolicy>,
+    this: WeakHandle,
+}
+
+enum Transport {
+    Tcp(Tcp),
+    Custom(Box<dyn RpcTransport>),
+}
+
+/// The connections to a kRPC server.
+struct Tcp {
     rpc: Mutex<TcpStream>,
-    stream: Mutex<TcpStream>,
-    streams: StreamWrangler,
+    stream: Mutex<Option<TcpStream>>,
+    identifier: Mutex<Vec<u8>>,
+    connection: Option<Connection>,
+    reconnect: Mutex<Option<ReconnectPolicy>>,
+    /// Shared with the thread receiving stream updates.
+    max_message_size: Arc<AtomicUsize>,
+}
+
+type ReconnectHook = Arc<dyn Fn(&ClientHandle) + Send + Sync>;
+type CallHook = Arc<dyn Fn(&str, &str, Duration) + Send + Sync>;
+
+/// How a [`Client`] re-establishes a lost connection.
+///
+/// Each attempt waits for the current backoff before
+/// reconnecting, and the backoff doubles after every failed
+/// attempt up to `max_backoff`.
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    /// How many times to try reconnecting before giving up.
+    pub max_retries: u32,
+    /// How long to wait before the first attempt.
+    pub initial_backoff: Duration,
+    /// The longest to wait between attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Which errors raised by the server a [`Client`] retries, and
+/// how.
+///
+/// Some errors are transient, such as those thrown while a
+/// vessel is still loading or the game is changing scene, and
+/// the same call succeeds a moment later. A call failing with
+/// an exception whose [`name`][name] is in `errors` is made
+/// again after `delay`, up to `max_retries` times, before the
+/// error is returned.
+///
+/// The default retries nothing.
+///
+/// ```no_run
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
+/// use std::time::Duration;
+///
+/// use krpc_client::{client::RetryPolicy, Client};
+/// let client = Client::builder()
+///     .retry_policy(RetryPolicy {
+///         errors: vec!["InvalidOperationException".into()],
+///         max_retries: 3,
+///         delay: Duration::from_millis(200),
+///     })
+///     .connect()?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [name]: crate::error::RemoteError::name
+#[derive(Clone, Debug, Default)]
+pub struct RetryPolicy {
+    /// The names of the exceptions to retry.
+    pub errors: Vec<String>,
+    /// How many times to retry a call before giving up.
+    pub max_retries: u32,
+    /// How long to wait before each retry.
+    pub delay: Duration,
+}
+
+impl RetryPolicy {
+    /// Whether a call failing with `error` should be retried.
+    fn retries(&self, error: &RpcError) -> bool {
+        match error {
+            RpcError::Remote(remote) => self.errors.contains(&remote.name),
+            _ => false,
+        }
+    }
+}
+
+/// Client-side statistics, gathered since the client was
+/// constructed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClientStats {
+    /// Bytes written to the RPC connection.
+    pub bytes_sent: u64,
+    /// Bytes read from the RPC connection.
+    pub bytes_received: u64,
+    /// Procedure calls made, counting each call in a batch.
+    pub calls: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    bytes_sent: AtomicU64,
+    bytes_received: AtomicU64,
+    calls: AtomicU64,
+}
+
+impl Counters {
+    fn snapshot(&self) -> ClientStats {
+        ClientStats {
+            bytes_sent: self.bytes_sent.load(Ordering::Relaxed),
+            bytes_received: self.bytes_received.load(Ordering::Relaxed),
+            calls: self.calls.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Everything needed to (re-)establish a client's
+/// connections.
+struct Connection {
+    name: String,
+    ip_addr: String,
+    rpc_port: u16,
+    stream_port: Option<u16>,
+    timeout: Option<Duration>,
+}
+
+/// The sockets of an established connection, and the
+/// identifier the server assigned to it.
+struct Session {
+    rpc: TcpStream,
+    stream: Option<TcpStream>,
+    identifier: Vec<u8>,
+}
+
+impl Connection {
+    /// Connect and handshake the RPC socket, and the stream
+    /// socket if there is one.
+    fn open(&self) -> Result<Session, RpcError> {
+        let rpc = connect(&self.ip_addr, self.rpc_port, self.timeout)?;
+        let (rpc, identifier) = handshake_rpc(&self.name, rpc, self.timeout)?;
+        let stream = match self.stream_port {
+            Some(port) => {
+                let stream = connect(&self.ip_addr, port, self.timeout)?;
+                Some(handshake_stream(
+                    &self.name,
+                    stream,
+                    &identifier,
+                    self.timeout,
+                )?)
+            }
+            None => None,
+        };
+
+        Ok(Session {
+            rpc,
+            stream,
+            identifier,
+        })
+    }
+}
+
+/// Builds a [`Client`] with options beyond those taken by
+/// [`Client::new`]. Start one with [`Client::builder`].
+///
+/// The client connects to `127.0.0.1` on kRPC's default ports,
+/// `50000` for RPCs and `50001` for streams, unless told
+/// otherwise.
+#[derive(Clone, Debug)]
+pub struct ClientBuilder {
+    name: String,
+    ip_addr: String,
+    rpc_port: u16,
+    stream_port: u16,
+    timeout: Option<Duration>,
+    streams: bool,
+    retry: RetryPolicy,
+    #[cfg(not(feature = "rc"))]
+    keepalive: Option<Duration>,
+    max_message_size: usize,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            ip_addr: "127.0.0.1".into(),
+            rpc_port: 50000,
+            stream_port: 50001,
+            timeout: None,
+            streams: true,
+            retry: RetryPolicy::default(),
+            #[cfg(not(feature = "rc"))]
+            keepalive: None,
+            max_message_size: MAX_MESSAGE_SIZE,
+        }
+    }
+}
+
+impl ClientBuilder {
+    /// The name the server shows for this client.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// The server's address, which may also be a hostname or
+    /// an IPv6 address.
+    pub fn address(mut self, ip_addr: &str) -> Self {
+        self.ip_addr = ip_addr.into();
+        self
+    }
+
+    /// The port of the server's RPC connection.
+    pub fn rpc_port(mut self, port: u16) -> Self {
+        self.rpc_port = port;
+        self
+    }
+
+    /// The port of the server's stream connection.
+    pub fn stream_port(mut self, port: u16) -> Self {
+        self.stream_port = port;
+        self
+    }
+
+    /// Fail with [`RpcError::Timeout`] if either connection or
+    /// its handshake takes longer than `timeout`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Whether to open the stream connection. Without it,
+    /// creating a [`Stream`] or an [`Event`][event] fails
+    /// with [`RpcError::Connection`].
+    ///
+    /// [event]: crate::stream::Event
+    pub fn enable_streams(mut self, enable: bool) -> Self {
+        self.streams = enable;
+        self
+    }
+
+    /// Which errors raised by the server to retry. See
+    /// [`RetryPolicy`].
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Check the connection every `interval` by asking for the
+    /// server's status, which is off by default.
+    ///
+    /// If the check fails, the connections are closed, so the
+    /// next call fails with [`RpcError::Connection`] rather
+    /// than waiting on a server that has gone away, and a
+    /// [reconnect policy][reconnect] can take over. A server
+    /// that stops responding without closing the connection is
+    /// only noticed with a [read timeout][read_timeout].
+    ///
+    /// [reconnect]: Client::set_reconnect_policy
+    /// [read_timeout]: Client::set_read_timeout
+    #[cfg(not(feature = "rc"))]
+    pub fn keepalive(mut self, interval: Duration) -> Self {
+        self.keepalive = Some(interval);
+        self
+    }
+
+    /// The largest message to accept from the server, in
+    /// bytes. See [`Client::set_max_message_size`].
+    pub fn max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Connect to the server.
+    pub fn connect(self) -> Result<ClientHandle, RpcError> {
+        let connection = Connection {
+            name: self.name,
+            ip_addr: self.ip_addr,
+            rpc_port: self.rpc_port,
+            stream_port: self.streams.then_some(self.stream_port),
+            timeout: self.timeout,
+        };
+        let session = connection.open()?;
+        let client = Client::from_session(session, Some(connection))?;
+        client.set_retry_policy(self.retry)?;
+        client.set_max_message_size(self.max_message_size);
+        #[cfg(not(feature = "rc"))]
+        if let Some(interval) = self.keepalive {
+            spawn_keepalive_thread(&client, interval);
+        }
+        Ok(client)
+    }
+
+    /// Connect only to the server's stream connection, to
+    /// receive the streams of the client with the given
+    /// `identifier`, such as those another process has set up.
+    ///
+    /// Without an RPC connection, no procedures can be called,
+    /// so every call fails with [`RpcError::Connection`].
+    /// Attach to existing streams with
+    /// [`Client::stream_from_id`].
+    ///
+    /// The identifier is the other client's
+    /// [`identifier`][identifier], which is plain bytes for
+    /// passing between processes however suits them. It only
+    /// stays valid while that client's RPC connection is open:
+    /// the server forgets it, and stops the streams, once the
+    /// connection closes, and a client that reconnects is given
+    /// a new identifier.
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::Client;
+    /// # let (identifier, id) = (vec![], 1);
+    /// let client = Client::builder().connect_stream_only(&identifier)?;
+    /// let altitude = client.stream_from_id::<f64>(id)?;
+    /// println!("{}", altitude.get()?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [identifier]: Client::identifier
+    pub fn connect_stream_only(
+        self,
+        identifier: &[u8],
+    ) -> Result<ClientHandle, RpcError> {
+        let stream = connect(&self.ip_addr, self.stream_port, self.timeout)?;
+        let stream =
+            handshake_stream(&self.name, stream, identifier, self.timeout)?;
+
+        let streams = Arc::new(StreamWrangler::default());
+        spawn_stream_thread(
+            &stream,
+            streams.clone(),
+            Arc::new(AtomicUsize::new(self.max_message_size)),
+        )?;
+        let transport = Transport::Custom(Box::new(StreamOnly(stream)));
+        Ok(Client::from_transport(transport, streams))
+    }
+}
+
+/// The transport of a client connected only for streams.
+struct StreamOnly(TcpStream);
+
+impl RpcTransport for StreamOnly {
+    fn call(
+        &self,
+        _request: &schema::Request,
+    ) -> Result<schema::Response, RpcError> {
+        Err(RpcError::Connection(io::Error::new(
+            io::ErrorKind::NotConnected,
+            "client is connected only for streams",
+        )))
+    }
+}
+
+impl Drop for StreamOnly {
+    // Stops the background thread receiving updates.
+    fn drop(&mut self) {
+        self.0.shutdown(Shutdown::Both).ok();
+    }
 }
 
 impl Client {
     /// Constructs a new `Client`.
     ///
+    /// `ip_addr` may also be a hostname or an IPv6 address,
+    /// and each address it resolves to is tried in turn.
+    ///
     /// # Examples
     ///
     /// ```
@@ -62,50 +532,941 @@ impl Client {
         ip_addr: &str,
         rpc_port: u16,
         stream_port: u16,
-    ) -> Result<Arc<Self>, RpcError> {
-        let rpc_request = schema::ConnectionRequest {
-            type_: protobuf::EnumOrUnknown::new(connection_request::Type::RPC),
-            client_name: String::from(name),
-            ..Default::default()
-        };
-        let (rpc_stream, rpc_result) = connect(ip_addr, rpc_port, rpc_request)?;
-
-        let stream_request = schema::ConnectionRequest {
-            type_: protobuf::EnumOrUnknown::new(
-                connection_request::Type::STREAM,
-            ),
-            client_name: String::from(name),
-            client_identifier: rpc_result.client_identifier,
-            ..Default::default()
+    ) -> Result<ClientHandle, RpcError> {
+        Self::builder()
+            .name(name)
+            .address(ip_addr)
+            .rpc_port(rpc_port)
+            .stream_port(stream_port)
+            .connect()
+    }
+
+    /// Constructs a new `Client`, failing with
+    /// [`RpcError::Timeout`] if either connection or its
+    /// handshake takes longer than `timeout`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// use krpc_client::Client;
+    /// let client = Client::connect_with_timeout(
+    ///     "Test KRPC",
+    ///     "127.0.0.1",
+    ///     50000,
+    ///     50001,
+    ///     Duration::from_secs(5),
+    /// );
+    /// ```
+    pub fn connect_with_timeout(
+        name: &str,
+        ip_addr: &str,
+        rpc_port: u16,
+        stream_port: u16,
+        timeout: Duration,
+    ) -> Result<ClientHandle, RpcError> {
+        Self::builder()
+            .name(name)
+            .address(ip_addr)
+            .rpc_port(rpc_port)
+            .stream_port(stream_port)
+            .timeout(timeout)
+            .connect()
+    }
+
+    /// Starts building a `Client`, for setting connection
+    /// options that [`Client::new`] doesn't take.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use krpc_client::Client;
+    /// let client = Client::builder()
+    ///     .name("Test KRPC")
+    ///     .enable_streams(false)
+    ///     .connect();
+    /// ```
+    pub fn builder() -> ClientBuilder {
+        ClientBuilder::default()
+    }
+
+    /// Constructs a new `Client` over sockets that are already
+    /// connected to the server's RPC and stream ports, such as
+    /// ones tunnelled through a proxy.
+    ///
+    /// Having no address to dial, the client can't reconnect
+    /// if either connection is lost.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use std::net::TcpStream;
+    ///
+    /// use krpc_client::Client;
+    /// let rpc = TcpStream::connect("127.0.0.1:50000")?;
+    /// let stream = TcpStream::connect("127.0.0.1:50001")?;
+    /// let client = Client::from_streams("Test KRPC", rpc, stream)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_streams(
+        name: &str,
+        rpc: TcpStream,
+        stream: TcpStream,
+    ) -> Result<ClientHandle, RpcError> {
+        let (rpc, identifier) = handshake_rpc(name, rpc, None)?;
+        let stream = handshake_stream(name, stream, &identifier, None)?;
+        let session = Session {
+            rpc,
+            stream: Some(stream),
+            identifier,
         };
-        let (stream_stream, _) = connect(ip_addr, stream_port, stream_request)?;
+        Self::from_session(session, None)
+    }
 
-        let client = Arc::new(Self {
-            rpc: Mutex::new(rpc_stream),
-            stream: Mutex::new(stream_stream),
-            streams: StreamWrangler::default(),
-        });
+    fn from_session(
+        session: Session,
+        connection: Option<Connection>,
+    ) -> Result<ClientHandle, RpcError> {
+        let streams = Arc::new(StreamWrangler::default());
+        let max_message_size = Arc::new(AtomicUsize::new(MAX_MESSAGE_SIZE));
+        if let Some(stream) = &session.stream {
+            spawn_stream_thread(
+                stream,
+                streams.clone(),
+                max_message_size.clone(),
+            )?;
+        }
 
-        // Spawn a thread to receive stream updates.
-        let bg_client = client.clone();
-        thread::spawn(move || loop {
-            bg_client.update_streams().ok();
-        });
+        let tcp = Tcp {
+            rpc: Mutex::new(session.rpc),
+            stream: Mutex::new(session.stream),
+            identifier: Mutex::new(session.identifier),
+            connection,
+            reconnect: Mutex::new(None),
+            max_message_size,
+        };
+        let client = Self::from_transport(Transport::Tcp(tcp), streams);
+        client.check_version();
 
         Ok(client)
     }
 
-    pub(crate) fn call(
+    /// Constructs a `Client` that makes procedure calls
+    /// through `transport` rather than connecting to a server.
+    ///
+    /// Streams only receive the updates the transport delivers
+    /// to the [`StreamSink`] passed to
+    /// [`RpcTransport::connect_streams`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use krpc_client::{transport::MockClient, Client};
+    /// let mock = MockClient::new();
+    /// let client = Client::with_transport(mock.clone());
+    /// ```
+    pub fn with_transport(
+        transport: impl RpcTransport + 'static,
+    ) -> ClientHandle {
+        let streams = Arc::default();
+        transport.connect_streams(StreamSink::new(Arc::clone(&streams)));
+        Self::from_transport(Transport::Custom(Box::new(transport)), streams)
+    }
+
+    fn from_transport(
+        transport: Transport,
+        streams: Arc<StreamWrangler>,
+    ) -> ClientHandle {
+        ClientHandle::new_cyclic(|this| Self {
+            transport,
+            streams,
+            stats: Counters::default(),
+            on_reconnect: Mutex::new(None),
+            on_call: Mutex::new(None),
+            has_on_call: AtomicBool::new(false),
+            retry: Mutex::default(),
+            this: this.clone(),
+        })
+    }
+
+    fn tcp(&self) -> Option<&Tcp> {
+        match &self.transport {
+            Transport::Tcp(tcp) => Some(tcp),
+            Transport::Custom(_) => None,
+        }
+    }
+
+    /// The identifier the server assigned this client
+    /// during its handshake, which changes if the client
+    /// reconnects. Empty for a client with a custom
+    /// transport.
+    pub fn identifier(&self) -> Result<Vec<u8>, RpcError> {
+        match self.tcp() {
+            Some(tcp) => Ok(tcp.identifier.lock()?.clone()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Statistics kept by the client, as distinct from the
+    /// server's, which are part of its [`status`][status].
+    ///
+    /// Byte counts cover the RPC connection only, and are zero
+    /// for a client with a custom transport.
+    ///
+    /// [status]: Client::status
+    pub fn stats(&self) -> ClientStats {
+        self.stats.snapshot()
+    }
+
+    /// Retrieve the server's version and runtime
+    /// statistics.
+    pub fn status(self: &ClientHandle) -> Result<schema::Status, RpcError> {
+        KRPC::new(self.clone()).get_status()
+    }
+
+    /// Wait until the game is ready for calls, returning the
+    /// scene it is in.
+    ///
+    /// The game isn't ready while a call for its current scene
+    /// fails with an error raised by the server, as during a
+    /// scene change. Nor is it while the call fails with a
+    /// connection error, as while the game is still starting,
+    /// if the client has a [`ReconnectPolicy`] to recover with.
+    /// Tries again after a delay doubling from 100 ms up to
+    /// 2 s, and fails with [`RpcError::Timeout`] once `timeout`
+    /// has passed. Other errors are returned as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use std::time::Duration;
+    ///
+    /// use krpc_client::{services::krpc::GameScene, transport::MockClient};
+    /// let mock = MockClient::new();
+    /// mock.respond("KRPC", "get_CurrentGameScene", GameScene::Flight)?;
+    ///
+    /// let client = mock.client();
+    /// let scene = client.wait_for_ready(Duration::from_secs(10))?;
+    /// assert_eq!(scene, GameScene::Flight);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn wait_for_ready(
+        self: &ClientHandle,
+        timeout: Duration,
+    ) -> Result<GameScene, RpcError> {
+        // A timeout too long to represent never passes.
+        let deadline = Instant::now().checked_add(timeout);
+        let mut backoff = Duration::from_millis(100);
+        let krpc = KRPC::new(self.clone());
+        loop {
+            match krpc.current_game_scene() {
+                Err(e @ RpcError::Remote(_)) => {
+                    log::debug!("game not ready: {e}");
+                }
+                Err(e @ RpcError::Connection(_)) if self.reconnects()? => {
+                    log::debug!("game not ready: {e}");
+                }
+                result => return result,
+            }
+            let delay = match deadline {
+                Some(deadline) => {
+                    let remaining =
+                        deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(RpcError::Timeout);
+                    }
+                    backoff.min(remaining)
+                }
+                None => backoff,
+            };
+            thread::sleep(delay);
+            backoff = (backoff * 2).min(Duration::from_secs(2));
+        }
+    }
+
+    /// Whether the client reconnects after losing its
+    /// connection.
+    fn reconnects(&self) -> Result<bool, RpcError> {
+        match self.tcp() {
+            Some(tcp) => Ok(tcp.reconnect.lock()?.is_some()),
+            None => Ok(false),
+        }
+    }
+
+    /// Retrieve the definition of every service the server
+    /// provides: their procedures, classes, enumerations and
+    /// exceptions, as described by the JSON the services were
+    /// generated from.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{
+    ///     messages::{Procedure, Service, Services},
+    ///     transport::MockClient,
+    /// };
+    /// let mock = MockClient::new();
+    /// # let procedure = Procedure {
+    /// #     name: "get_ActiveVessel".into(),
+    /// #     ..Default::default()
+    /// # };
+    /// # let service = Service {
+    /// #     name: "SpaceCenter".into(),
+    /// #     procedures: vec![procedure],
+    /// #     ..Default::default()
+    /// # };
+    /// # let services = Services {
+    /// #     services: vec![service],
+    /// #     ..Default::default()
+    /// # };
+    /// # mock.respond("KRPC", "GetServices", services)?;
+    /// let services = mock.client().services()?;
+    /// for service in &services.services {
+    ///     for procedure in &service.procedures {
+    ///         println!("{}.{}", service.name, procedure.name);
+    ///     }
+    /// }
+    /// # assert_eq!(services.services[0].procedures[0].name, "get_ActiveVessel");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn services(self: &ClientHandle) -> Result<schema::Services, RpcError> {
+        KRPC::new(self.clone()).get_services()
+    }
+
+    /// Warn if the server's kRPC version differs from the one
+    /// the services were generated from, as procedures may be
+    /// missing, or their results decode differently.
+    fn check_version(self: &ClientHandle) {
+        let Some(version) = KRPC_VERSION else {
+            return;
+        };
+        match self.status() {
+            Ok(status) if !same_version(&status.version, version) => {
+                log::warn!(
+                    "server kRPC version {} differs from {version}, \
+                     which the services were generated from",
+                    status.version
+                )
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("unable to check server version: {e}"),
+        }
+    }
+
+    /// Sets how long a procedure call may wait for the
+    /// server's response before failing with
+    /// [`RpcError::Timeout`]. `None` waits indefinitely, which
+    /// is the default.
+    ///
+    /// A response that arrives after its call timed out is
+    /// not recoverable, so the connection should be considered
+    /// broken after a timeout.
+    ///
+    /// Has no effect on a client with a custom transport.
+    pub fn set_read_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> Result<(), RpcError> {
+        if let Some(tcp) = self.tcp() {
+            tcp.rpc.lock()?.set_read_timeout(timeout)?;
+        }
+        Ok(())
+    }
+
+    /// Sets the largest message to accept from the server, in
+    /// bytes, which is 64 MiB by default.
+    ///
+    /// Messages are read whole, however large, so this guards
+    /// against a corrupt length allocating more memory than
+    /// the system has. A longer message fails with
+    /// [`RpcError::Encoding`], leaving the connection unusable.
+    ///
+    /// Has no effect on a client with a custom transport.
+    pub fn set_max_message_size(&self, bytes: usize) {
+        if let Some(tcp) = self.tcp() {
+            tcp.max_message_size.store(bytes, Ordering::Relaxed);
+        }
+    }
+
+    /// Sets whether procedure calls are sent immediately
+    /// (`true`, the default) or may be buffered by Nagle's
+    /// algorithm, trading latency for throughput.
+    ///
+    /// Has no effect on a client with a custom transport.
+    pub fn set_nodelay(&self, nodelay: bool) -> Result<(), RpcError> {
+        if let Some(tcp) = self.tcp() {
+            tcp.rpc.lock()?.set_nodelay(nodelay)?;
+        }
+        Ok(())
+    }
+
+    /// Sets how the client reconnects after losing its
+    /// connection to the server. `None`, the default, reports
+    /// the loss as [`RpcError::Connection`] instead.
+    ///
+    /// Once reconnected, the call that found the connection
+    /// broken is retried once. That call may already have run
+    /// on the server, so procedures with side effects could
+    /// run twice.
+    ///
+    /// Has no effect on a client with a custom transport.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{client::ReconnectPolicy, Client};
+    /// # let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
+    /// client.set_reconnect_policy(Some(ReconnectPolicy::default()))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_reconnect_policy(
+        &self,
+        policy: Option<ReconnectPolicy>,
+    ) -> Result<(), RpcError> {
+        if let Some(tcp) = self.tcp() {
+            *tcp.reconnect.lock()? = policy;
+        }
+        Ok(())
+    }
+
+    /// Sets which errors raised by the server the client
+    /// retries. See [`RetryPolicy`].
+    ///
+    /// A retried call runs again on the server, so only retry
+    /// errors thrown before a procedure has any effect.
+    pub fn set_retry_policy(
+        &self,
+        policy: RetryPolicy,
+    ) -> Result<(), RpcError> {
+        *self.retry.lock()? = policy;
+        Ok(())
+    }
+
+    /// Sets a hook to run after the client reconnects.
+    ///
+    /// The server forgets a client's streams when its
+    /// connection is lost, so existing [`Stream`]s stop
+    /// updating; use this hook to create them again.
+    pub fn on_reconnect(
+        &self,
+        hook: impl Fn(&ClientHandle) + Send + Sync + 'static,
+    ) -> Result<(), RpcError> {
+        *self.on_reconnect.lock()? = Some(Arc::new(hook));
+        Ok(())
+    }
+
+    /// Sets a hook to run after each procedure call, with its
+    /// service, procedure and how long the server took to
+    /// respond, for finding which calls take the most time.
+    ///
+    /// Calls made in one request, such as a batch, are each
+    /// reported with the time taken by the whole request. A
+    /// call that fails to get a response isn't reported.
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// use krpc_client::{
+    ///     services::space_center::SpaceCenter, transport::MockClient,
+    /// };
+    /// let mock = MockClient::new();
+    /// mock.respond("SpaceCenter", "get_UT", 10.0)?;
+    /// let client = mock.client();
+    ///
+    /// let called = Arc::new(Mutex::new(Vec::new()));
+    /// let log = called.clone();
+    /// client.on_call(move |service, procedure, _elapsed| {
+    ///     log.lock().unwrap().push(format!("{service}.{procedure}"));
+    /// })?;
+    ///
+    /// SpaceCenter::new(client).ut()?;
+    /// assert_eq!(*called.lock().unwrap(), ["SpaceCenter.get_UT"]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn on_call(
+        &self,
+        hook: impl Fn(&str, &str, Duration) + Send + Sync + 'static,
+    ) -> Result<(), RpcError> {
+        *self.on_call.lock()? = Some(Arc::new(hook));
+        self.has_on_call.store(true, Ordering::Release);
+        Ok(())
+    }
+
+    /// Send a request of any number of calls, built from the
+    /// protobuf [`messages`][messages], returning the server's
+    /// response.
+    ///
+    /// Errors raised by the server are returned as
+    /// [`RpcError::Remote`], as for any other call.
+    ///
+    /// [messages]: crate::messages
+    pub fn call(
         &self,
         request: schema::Request,
     ) -> Result<schema::Response, RpcError> {
-        let mut rpc = self.rpc.lock().map_err(|_| RpcError::Client)?;
+        self.call_encoded(&request, None)
+    }
+
+    /// Send a single procedure call, returning the server's
+    /// response.
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{
+    ///     messages::ProcedureCall, services::space_center::SpaceCenter,
+    ///     transport::MockClient,
+    /// };
+    /// let mock = MockClient::new();
+    /// mock.respond("SpaceCenter", "get_UT", 10.0)?;
+    /// let client = mock.client();
+    ///
+    /// let call: ProcedureCall = SpaceCenter::new(client.clone()).ut_call()?;
+    /// let response = client.call_proto(call)?;
+    /// assert_eq!(response.results[0].value, 10.0f64.to_le_bytes());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_proto(
+        &self,
+        call: schema::ProcedureCall,
+    ) -> Result<schema::Response, RpcError> {
+        self.call(schema::Request::from(call))
+    }
+
+    /// Send a request as [`call`][call] does, also returning the
+    /// response exactly as the server sent it, without its
+    /// length prefix.
+    ///
+    /// This is a debugging aid for when a generated type
+    /// disagrees with the server, such as after a kRPC version
+    /// bump, as the bytes are returned even if the response
+    /// fails to parse. The request is sent once, without the
+    /// retries, reconnection or [`on_call`][on_call] hook of
+    /// other calls.
+    ///
+    /// A client with a custom transport never sees the bytes,
+    /// so returns the response encoded again instead.
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{
+    ///     services::space_center::SpaceCenter, transport::MockClient,
+    /// };
+    /// let mock = MockClient::new();
+    /// mock.respond("SpaceCenter", "get_UT", 10.0)?;
+    /// let client = mock.client();
+    ///
+    /// let call = SpaceCenter::new(client.clone()).ut_call()?;
+    /// let (response, bytes) = client.call_debug(call.into());
+    /// assert_eq!(response?.results[0].value, 10.0f64.to_le_bytes());
+    /// // Field 2, `results`, holding field 2, `value`, of 8 bytes.
+    /// assert_eq!(bytes[..4], [0x12, 10, 0x12, 8]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [call]: Client::call
+    /// [on_call]: Client::on_call
+    pub fn call_debug(
+        &self,
+        request: schema::Request,
+    ) -> (Result<schema::Response, RpcError>, Vec<u8>) {
+        self.stats
+            .calls
+            .fetch_add(request.calls.len() as u64, Ordering::Relaxed);
+        let (response, bytes) = match &self.transport {
+            Transport::Tcp(tcp) => {
+                match request.write_length_delimited_to_bytes() {
+                    Ok(encoded) => {
+                        match tcp.send_request_raw(&encoded, &self.stats) {
+                            Ok((bytes, _)) => (
+                                schema::Response::parse_from_bytes(&bytes)
+                                    .map_err(RpcError::from),
+                                bytes,
+                            ),
+                            Err(e) => (Err(e), Vec::new()),
+                        }
+                    }
+                    Err(e) => (Err(e.into()), Vec::new()),
+                }
+            }
+            Transport::Custom(transport) => match transport.call(&request) {
+                Ok(response) => {
+                    let bytes = response.write_to_bytes().unwrap_or_default();
+                    (Ok(response), bytes)
+                }
+                Err(e) => (Err(e), Vec::new()),
+            },
+        };
+        let response = response.and_then(|response| {
+            check_results(&request, &response)?;
+            Ok(response)
+        });
+        (response, bytes)
+    }
+
+    /// Perform `request` and decode its first result, as the
+    /// generated procedures do.
+    #[doc(hidden)]
+    pub fn invoke<T: FromResponse>(
+        self: &ClientHandle,
+        request: schema::Request,
+    ) -> Result<T, RpcError> {
+        let response = self.call(request)?;
+        T::from_response(response, self.clone())
+    }
+
+    /// Perform `request`, which may already be `encoded` as a
+    /// length delimited message.
+    pub(crate) fn call_encoded(
+        &self,
+        request: &schema::Request,
+        encoded: Option<&[u8]>,
+    ) -> Result<schema::Response, RpcError> {
+        self.with_retries(|| {
+            let response = self.call_once(request, encoded)?;
+            check_results(request, &response)?;
+            Ok(response)
+        })
+    }
+
+    /// Run `call` until it succeeds, or fails with an error the
+    /// [`RetryPolicy`] doesn't retry, or runs out of retries.
+    fn with_retries<T>(
+        &self,
+        mut call: impl FnMut() -> Result<T, RpcError>,
+    ) -> Result<T, RpcError> {
+        // Only read once a call fails, so that calls that
+        // succeed needn't take the lock.
+        let mut policy = None;
+        let mut retries = 0;
+        loop {
+            let e = match call() {
+                Err(e) => e,
+                result => return result,
+            };
+            let policy = match &policy {
+                Some(policy) => policy,
+                None => policy.insert(self.retry.lock()?.clone()),
+            };
+            if retries >= policy.max_retries || !policy.retries(&e) {
+                return Err(e);
+            }
+            retries += 1;
+            log::debug!("retrying after error: {e}");
+            thread::sleep(policy.delay);
+        }
+    }
+
+    /// Perform `request` once, failing only for an error with
+    /// the request as a whole, not those of individual calls.
+    fn call_once(
+        &self,
+        request: &schema::Request,
+        encoded: Option<&[u8]>,
+    ) -> Result<schema::Response, RpcError> {
+        for call in &request.calls {
+            log::debug!("calling {}.{}", call.service, call.procedure);
+        }
+        log::trace!("sending request of {} bytes", request.compute_size());
+        self.stats
+            .calls
+            .fetch_add(request.calls.len() as u64, Ordering::Relaxed);
+
+        let hook = match self.has_on_call.load(Ordering::Acquire) {
+            true => self.on_call.lock()?.clone(),
+            false => None,
+        };
+        let (response, elapsed) = match (&self.transport, encoded) {
+            (Transport::Tcp(tcp), Some(encoded)) => {
+                self.call_tcp(tcp, encoded)?
+            }
+            (Transport::Tcp(tcp), None) => {
+                self.call_tcp(tcp, &request.write_length_delimited_to_bytes()?)?
+            }
+            (Transport::Custom(transport), _) => {
+                let start = Instant::now();
+                let response = transport.call(request)?;
+                (response, start.elapsed())
+            }
+        };
+        if let Some(hook) = hook {
+            for call in &request.calls {
+                hook(&call.service, &call.procedure, elapsed);
+            }
+        }
+
+        check_error(request, &response)?;
+        Ok(response)
+    }
+
+    fn call_tcp(
+        &self,
+        tcp: &Tcp,
+        request: &[u8],
+    ) -> Result<(schema::Response, Duration), RpcError> {
+        match tcp.send_request(request, &self.stats) {
+            Err(RpcError::Connection(e)) => {
+                let policy = tcp.reconnect.lock()?.clone();
+                let Some(policy) = policy else {
+                    return Err(RpcError::Connection(e));
+                };
+                log::warn!("connection lost ({e}), reconnecting");
+                tcp.reconnect(&policy, &self.streams)?;
+                self.run_reconnect_hook()?;
+                tcp.send_request(request, &self.stats)
+            }
+            result => result,
+        }
+    }
+
+    fn run_reconnect_hook(&self) -> Result<(), RpcError> {
+        // Release the lock before running the hook, which may
+        // itself make calls.
+        let hook = self.on_reconnect.lock()?.clone();
+        if let (Some(hook), Some(client)) = (hook, self.this.upgrade()) {
+            hook(&client);
+        }
+        Ok(())
+    }
+
+    /// Encode a procedure call once, so that it can be made
+    /// repeatedly without re-encoding.
+    ///
+    /// See [`PreparedCall`] for details.
+    pub fn prepare<T: RpcType>(
+        self: &ClientHandle,
+        call: schema::ProcedureCall,
+    ) -> Result<PreparedCall<T>, RpcError> {
+        PreparedCall::new(self.clone(), call)
+    }
 
-        send(&mut rpc, request)?;
-        recv(&mut rpc)
+    /// Stream the result of any procedure call, such as one
+    /// built at runtime. The generated `_stream()` procedures
+    /// call this.
+    ///
+    /// Returns once the stream's first value has arrived.
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{
+    ///     services::space_center::SpaceCenter, transport::MockClient,
+    /// };
+    /// let mock = MockClient::new();
+    /// mock.respond("SpaceCenter", "get_UT", 10.0)?;
+    /// let client = mock.client();
+    /// let space_center = SpaceCenter::new(client.clone());
+    ///
+    /// let ut = client.add_stream::<f64>(space_center.ut_call()?)?;
+    /// assert_eq!(ut.get()?, 10.0);
+    ///
+    /// mock.respond("SpaceCenter", "get_UT", 20.0)?;
+    /// mock.update_streams()?;
+    /// assert_eq!(ut.get()?, 20.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_stream<T: RpcType>(
+        self: &ClientHandle,
+        call: schema::ProcedureCall,
+    ) -> Result<Stream<T>, RpcError> {
+        Stream::new(self.clone(), call)
+    }
+
+    /// Receive a stream that already exists on the server by
+    /// its ID, such as one added by another process for a
+    /// client connected with [`connect_stream_only`][only].
+    ///
+    /// Unlike [`add_stream`][add_stream], this doesn't wait for
+    /// a first value; [`Stream::get`] does instead.
+    ///
+    /// # Examples
+    ///
+    /// Two clients of one mock share its streams, as two
+    /// clients of a server do when one is connected only for
+    /// the other's streams:
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{
+    ///     services::space_center::SpaceCenter, transport::MockClient,
+    /// };
+    /// let mock = MockClient::new();
+    /// mock.respond("SpaceCenter", "get_UT", 10.0)?;
+    /// let (owner, attached) = (mock.client(), mock.client());
+    ///
+    /// let ut = SpaceCenter::new(owner).ut_stream()?;
+    /// let shared = attached.stream_from_id::<f64>(ut.id().unwrap())?;
+    ///
+    /// mock.respond("SpaceCenter", "get_UT", 20.0)?;
+    /// mock.update_streams()?;
+    /// assert_eq!(shared.get()?, 20.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [only]: ClientBuilder::connect_stream_only
+    /// [add_stream]: Client::add_stream
+    pub fn stream_from_id<T: RpcType>(
+        self: &ClientHandle,
+        id: u64,
+    ) -> Result<Stream<T>, RpcError> {
+        Stream::from_id(self.clone(), id)
     }
 
-    pub(crate) fn proc_call(
+    /// Stream several procedure calls together, as a tuple
+    /// with an element for each call, in order.
+    ///
+    /// Each call is streamed separately by the server, and
+    /// the tuple updates whenever any of them does, holding
+    /// the latest value of every call. Values the server sends
+    /// in the same update, which it does for those computed on
+    /// the same game update, are seen together. The stream has
+    /// no value until every call has one.
+    ///
+    /// # Examples
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{
+    ///     services::space_center::SpaceCenter, transport::MockClient,
+    /// };
+    /// let mock = MockClient::new();
+    /// mock.respond("SpaceCenter", "get_UT", 10.0)?;
+    /// mock.respond("SpaceCenter", "get_WarpRate", 1.0f32)?;
+    /// let client = mock.client();
+    /// let space_center = SpaceCenter::new(client.clone());
+    ///
+    /// let time = client.add_combined_stream::<(f64, f32)>(vec![
+    ///     space_center.ut_call()?,
+    ///     space_center.warp_rate_call()?,
+    /// ])?;
+    /// assert_eq!(time.get()?, (10.0, 1.0));
+    ///
+    /// mock.respond("SpaceCenter", "get_WarpRate", 4.0f32)?;
+    /// mock.update_streams()?;
+    /// assert_eq!(time.get()?, (10.0, 4.0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn add_combined_stream<T: RpcType>(
+        self: &ClientHandle,
+        calls: Vec<schema::ProcedureCall>,
+    ) -> Result<Stream<T>, RpcError> {
+        Stream::combined(self.clone(), calls)
+    }
+
+    /// Perform several procedure calls in a single request,
+    /// returning a response for each call in the same order.
+    ///
+    /// Each call succeeds or fails on its own, so one failing
+    /// doesn't lose the results of the rest. Only a failure of
+    /// the request as a whole, such as a lost connection, fails
+    /// the batch. Such failures are retried by the
+    /// [`RetryPolicy`], as for any other call, but failed calls
+    /// aren't, as the others may already have taken effect.
+    ///
+    /// See [`Batch`][crate::batch::Batch] for a typed
+    /// interface.
+    #[allow(clippy::type_complexity)]
+    pub fn batch(
+        &self,
+        calls: Vec<schema::ProcedureCall>,
+    ) -> Result<Vec<Result<schema::Response, RpcError>>, RpcError> {
+        let results = self.call_batch(&calls)?;
+        Ok(calls
+            .iter()
+            .zip(&results)
+            .map(|(call, result)| call_response(call, result))
+            .collect())
+    }
+
+    /// Perform `calls` in a single request, returning each
+    /// call's result, failed or not.
+    pub(crate) fn call_batch(
+        &self,
+        calls: &[schema::ProcedureCall],
+    ) -> Result<Vec<schema::ProcedureResult>, RpcError> {
+        let request = schema::Request {
+            calls: calls.to_vec(),
+            ..Default::default()
+        };
+        self.with_retries(|| self.call_once(&request, None))
+            .map(|response| response.results)
+    }
+
+    /// Call a procedure by name with pre-encoded arguments,
+    /// returning the encoded result.
+    ///
+    /// This allows calling procedures missing from the
+    /// generated services, such as those of a modded server.
+    /// Arguments and results use kRPC's protobuf encoding,
+    /// without field tags.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::Client;
+    /// # let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
+    /// let ut = client.call_raw("SpaceCenter", "get_UT", vec![])?;
+    /// let ut = f64::from_le_bytes(ut.try_into().unwrap());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_raw(
+        &self,
+        service: &str,
+        procedure: &str,
+        args: Vec<Vec<u8>>,
+    ) -> Result<Vec<u8>, RpcError> {
+        let args = (0..)
+            .zip(args)
+            .map(|(position, value)| schema::Argument {
+                position,
+                value,
+                ..Default::default()
+            })
+            .collect();
+        let request =
+            schema::Request::from(Self::proc_call(service, procedure, args));
+        let response = self.call(request)?;
+
+        response
+            .results
+            .into_iter()
+            .next()
+            .map(|result| result.value)
+            .ok_or_else(|| RpcError::Encoding("missing result".into()))
+    }
+
+    #[doc(hidden)]
+    pub fn proc_call(
         service: &str,
         procedure: &str,
         args: Vec<schema::Argument>,
@@ -118,68 +1479,535 @@ impl Client {
         }
     }
 
-    pub(crate) fn update_streams(self: &Arc<Self>) -> Result<(), RpcError> {
-        let mut stream = self.stream.lock()?;
-        let update = recv::<StreamUpdate>(&mut stream)?;
-        for result in update.results {
-            self.streams.insert(
-                result.id,
-                result.result.into_option().ok_or(RpcError::Client)?,
-            )?;
+    /// Fail if this client was built without a stream
+    /// connection, as its streams would never be updated.
+    pub(crate) fn check_streams(&self) -> Result<(), RpcError> {
+        match self.tcp() {
+            Some(tcp) if tcp.stream.lock()?.is_none() => {
+                Err(RpcError::Connection(io::ErrorKind::NotConnected.into()))
+            }
+            _ => Ok(()),
         }
-        Ok(())
     }
 
-    pub(crate) fn read_stream<T: DecodeUntagged>(
-        self: &Arc<Self>,
-        id: u64,
-    ) -> Result<T, RpcError> {
-        self.streams.get(self.clone(), id)
+    pub(crate) fn acquire_stream(&self, id: u64) -> Result<(), RpcError> {
+        self.streams.acquire(id)
     }
 
+    /// Release a handle to a stream, removing it from the
+    /// server once no handles remain.
     pub(crate) fn remove_stream(
-        self: &Arc<Self>,
+        self: &ClientHandle,
         id: u64,
     ) -> Result<(), RpcError> {
-        self.streams.remove(id);
+        if self.streams.release(id)? {
+            KRPC::new(self.clone()).remove_stream(id)?;
+        }
         Ok(())
     }
 
-    pub(crate) fn await_stream(&self, id: u64) {
-        self.streams.wait(id)
+    /// Remove every stream this client has added from the
+    /// server, such as before disconnecting cleanly.
+    ///
+    /// Existing [`Stream`]s keep their last value, but receive
+    /// no more updates. Every stream is removed even if some
+    /// fail, and the first failure is returned.
+    pub fn remove_all_streams(self: &ClientHandle) -> Result<(), RpcError> {
+        let krpc = KRPC::new(self.clone());
+        self.streams
+            .take_all()?
+            .into_iter()
+            .map(|id| krpc.remove_stream(id))
+            .fold(Ok(()), Result::and)
+    }
+
+    pub(crate) fn combine_streams(
+        &self,
+        ids: Vec<u64>,
+    ) -> Result<u64, RpcError> {
+        self.streams.add_combined(ids)
+    }
+
+    pub(crate) fn remove_combined_stream(
+        &self,
+        id: u64,
+    ) -> Result<(), RpcError> {
+        self.streams.remove_combined(id)
+    }
+
+    pub(crate) fn await_stream(&self, key: StreamKey) {
+        self.streams.wait(key)
+    }
+
+    pub(crate) fn await_stream_newer<T: DecodeUntagged>(
+        self: &ClientHandle,
+        key: StreamKey,
+        seen: u64,
+        timeout: Option<Duration>,
+    ) -> Result<Option<(T, u64)>, RpcError> {
+        self.streams.wait_newer(self.clone(), key, seen, timeout)
+    }
+
+    pub(crate) fn await_stream_until<T: DecodeUntagged>(
+        self: &ClientHandle,
+        key: StreamKey,
+        timeout: Option<Duration>,
+        condition: impl Fn(&T) -> bool,
+    ) -> Result<Option<T>, RpcError> {
+        self.streams
+            .wait_until(self.clone(), key, timeout, condition)
     }
 }
 
+impl Tcp {
+    /// Send an encoded request and receive its response, with
+    /// how long the server took to respond.
+    fn send_request(
+        &self,
+        request: &[u8],
+        stats: &Counters,
+    ) -> Result<(schema::Response, Duration), RpcError> {
+        let (response, elapsed) = self.send_request_raw(request, stats)?;
+        Ok((schema::Response::parse_from_bytes(&response)?, elapsed))
+    }
+
+    /// Send an encoded request and receive its response,
+    /// unparsed, with how long the server took to respond.
+    fn send_request_raw(
+        &self,
+        request: &[u8],
+        stats: &Counters,
+    ) -> Result<(Vec<u8>, Duration), RpcError> {
+        let mut rpc = self.rpc.lock().map_err(|_| RpcError::Client)?;
+        // Only once the connection is ours, so as not to count
+        // time spent waiting for other threads' calls.
+        let start = Instant::now();
+        rpc.write_all(request).map_err(io_error)?;
+        stats
+            .bytes_sent
+            .fetch_add(request.len() as u64, Ordering::Relaxed);
+
+        let limit = self.max_message_size.load(Ordering::Relaxed);
+        let (response, received) = recv_raw(&mut rpc, limit)?;
+        stats.bytes_received.fetch_add(received, Ordering::Relaxed);
+        Ok((response, start.elapsed()))
+    }
+
+    /// Close both connections, failing any further calls until
+    /// the client reconnects.
+    #[cfg(not(feature = "rc"))]
+    fn disconnect(&self) {
+        if let Ok(Some(stream)) = self.stream.lock().as_deref() {
+            stream.shutdown(Shutdown::Both).ok();
+        }
+        if let Ok(rpc) = self.rpc.lock() {
+            rpc.shutdown(Shutdown::Both).ok();
+        }
+    }
+
+    /// Re-establish both connections according to `policy`.
+    fn reconnect(
+        &self,
+        policy: &ReconnectPolicy,
+        streams: &Arc<StreamWrangler>,
+    ) -> Result<(), RpcError> {
+        let Some(connection) = &self.connection else {
+            return Err(RpcError::Connection(
+                io::ErrorKind::NotConnected.into(),
+            ));
+        };
+
+        let mut backoff = policy.initial_backoff;
+        let mut last_error = RpcError::Client;
+        for attempt in 1..=policy.max_retries {
+            thread::sleep(backoff);
+            match connection.open() {
+                Ok(session) => {
+                    self.replace_session(session, streams)?;
+                    log::info!("reconnected after {attempt} attempt(s)");
+                    return Ok(());
+                }
+                Err(e) => {
+                    log::warn!("reconnection attempt {attempt} failed: {e}");
+                    last_error = e;
+                }
+            }
+            backoff = (backoff * 2).min(policy.max_backoff);
+        }
+
+        Err(last_error)
+    }
+
+    fn replace_session(
+        &self,
+        session: Session,
+        streams: &Arc<StreamWrangler>,
+    ) -> Result<(), RpcError> {
+        let Session {
+            rpc: rpc_stream,
+            stream: stream_stream,
+            identifier,
+        } = session;
+        if let Some(stream) = &stream_stream {
+            spawn_stream_thread(
+                stream,
+                streams.clone(),
+                self.max_message_size.clone(),
+            )?;
+        }
+        *self.identifier.lock()? = identifier;
+
+        let mut rpc = self.rpc.lock()?;
+        rpc_stream.set_read_timeout(rpc.read_timeout()?)?;
+        rpc_stream.set_nodelay(rpc.nodelay()?)?;
+        rpc.shutdown(Shutdown::Both).ok();
+        *rpc = rpc_stream;
+        drop(rpc);
+
+        // Shutting down the old stream socket stops its
+        // background thread.
+        let mut stream = self.stream.lock()?;
+        if let Some(stream) = stream.as_ref() {
+            stream.shutdown(Shutdown::Both).ok();
+        }
+        *stream = stream_stream;
+        Ok(())
+    }
+}
+
+impl Drop for Tcp {
+    // Streams hold a reference to the client and remove
+    // themselves when dropped, so none are left on the server
+    // by now. Shutting down the stream socket also stops the
+    // background thread receiving updates.
+    fn drop(&mut self) {
+        if let Ok(rpc) = self.rpc.get_mut() {
+            rpc.shutdown(Shutdown::Both).ok();
+        }
+        if let Ok(Some(stream)) = self.stream.get_mut() {
+            stream.shutdown(Shutdown::Both).ok();
+        }
+    }
+}
+
+/// Spawn a thread to receive stream updates. It holds its own
+/// handle to the socket, rather than the client, so that the
+/// client can still be dropped.
+fn spawn_stream_thread(
+    stream: &TcpStream,
+    streams: Arc<StreamWrangler>,
+    max_message_size: Arc<AtomicUsize>,
+) -> Result<(), RpcError> {
+    let mut stream = stream.try_clone()?;
+    // Opened before the thread starts, so that it replaces the
+    // connection of any thread it follows, and dropped as the
+    // thread exits to close the streams.
+    let sink = StreamSink::new(streams);
+    thread::spawn(move || loop {
+        let limit = max_message_size.load(Ordering::Relaxed);
+        if update_streams(&mut stream, &sink, limit).is_err() {
+            return;
+        }
+    });
+    Ok(())
+}
+
+/// Spawn a thread checking the connection every `interval`,
+/// until the client is dropped.
+#[cfg(not(feature = "rc"))]
+fn spawn_keepalive_thread(client: &ClientHandle, interval: Duration) {
+    let client = ClientHandle::downgrade(client);
+    thread::spawn(move || loop {
+        thread::sleep(interval);
+        let Some(client) = client.upgrade() else {
+            return;
+        };
+        if let Err(e) = client.status() {
+            log::warn!("keepalive failed ({e}), disconnecting");
+            if let Some(tcp) = client.tcp() {
+                tcp.disconnect();
+            }
+        }
+    });
+}
+
+/// Receive one batch of stream updates. Fails once the stream
+/// connection is closed.
+fn update_streams(
+    stream: &mut TcpStream,
+    sink: &StreamSink,
+    limit: usize,
+) -> Result<(), RpcError> {
+    sink.send(recv::<StreamUpdate>(stream, limit)?)
+}
+
+/// Connect to `host`, which may be a hostname or an IPv4 or
+/// IPv6 address, trying each address it resolves to in turn.
 fn connect(
-    ip_addr: &str,
+    host: &str,
     port: u16,
+    timeout: Option<Duration>,
+) -> Result<TcpStream, RpcError> {
+    let addrs = (host, port).to_socket_addrs()?.collect::<Vec<_>>();
+    let mut last_error = None;
+    for addr in &addrs {
+        let conn = match timeout {
+            Some(timeout) => TcpStream::connect_timeout(addr, timeout),
+            None => TcpStream::connect(addr),
+        };
+        match conn {
+            Ok(conn) => return Ok(conn),
+            Err(e) => {
+                log::debug!("unable to connect to {addr}: {e}");
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(match last_error {
+        Some(e) if is_timeout(&e) => RpcError::Timeout,
+        Some(e) => RpcError::Connection(io::Error::new(
+            e.kind(),
+            format!("unable to connect to any of {addrs:?}: {e}"),
+        )),
+        None => RpcError::Connection(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{host} did not resolve to any addresses"),
+        )),
+    })
+}
+
+/// Handshake an RPC connection, returning the identifier the
+/// server assigned the client.
+fn handshake_rpc(
+    name: &str,
+    conn: TcpStream,
+    timeout: Option<Duration>,
+) -> Result<(TcpStream, Vec<u8>), RpcError> {
+    let request = schema::ConnectionRequest {
+        type_: protobuf::EnumOrUnknown::new(connection_request::Type::RPC),
+        client_name: name.into(),
+        ..Default::default()
+    };
+    let (conn, response) = handshake(conn, request, timeout)?;
+    Ok((conn, response.client_identifier))
+}
+
+/// Handshake a stream connection for the client with the
+/// given identifier.
+fn handshake_stream(
+    name: &str,
+    conn: TcpStream,
+    identifier: &[u8],
+    timeout: Option<Duration>,
+) -> Result<TcpStream, RpcError> {
+    let request = schema::ConnectionRequest {
+        type_: protobuf::EnumOrUnknown::new(connection_request::Type::STREAM),
+        client_name: name.into(),
+        client_identifier: identifier.into(),
+        ..Default::default()
+    };
+    handshake(conn, request, timeout).map(|(conn, _)| conn)
+}
+
+fn handshake(
+    mut conn: TcpStream,
     request: ConnectionRequest,
+    timeout: Option<Duration>,
 ) -> Result<(TcpStream, ConnectionResponse), RpcError> {
-    let mut conn = TcpStream::connect(format!("{ip_addr}:{port}"))
-        .map_err(RpcError::Connection)?;
+    // kRPC exchanges many small messages, which Nagle's
+    // algorithm would delay.
+    conn.set_nodelay(true)?;
 
-    send(&mut conn, request)?;
-    let response = recv::<ConnectionResponse>(&mut conn)?;
+    let connection_type = request.type_;
+    let peer = conn.peer_addr()?;
+    conn.set_read_timeout(timeout)?;
+    send(&mut conn, &request)?;
+    let response = recv::<ConnectionResponse>(&mut conn, MAX_MESSAGE_SIZE)?;
     if response.status.value() != Status::OK as i32 {
-        return Err(RpcError::Client);
+        log::info!(
+            "{connection_type:?} handshake with {peer} failed: {}",
+            response.message
+        );
+        return Err(RpcError::Handshake(response.message));
     }
+    conn.set_read_timeout(None)?;
+    log::info!("{connection_type:?} connection established to {peer}");
 
     Ok((conn, response))
 }
 
+/// Compare versions by their numbers, so that `0.5` and
+/// `0.5.0` are the same.
+fn same_version(version: &str, other: &str) -> bool {
+    let parse = |v: &str| -> Vec<u32> {
+        let mut parts: Vec<u32> =
+            v.split('.').map(|part| part.parse().unwrap_or(0)).collect();
+        while parts.last() == Some(&0) {
+            parts.pop();
+        }
+        parts
+    };
+    parse(version) == parse(other)
+}
+
+fn is_timeout(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+    )
+}
+
+/// Classify a failure to send a message.
+fn transport_error(error: protobuf::Error) -> RpcError {
+    // Failures of the socket come back as they were, and
+    // failures to encode the message wrapped in an I/O error.
+    let error = io::Error::from(error);
+    if error.get_ref().is_some() {
+        RpcError::Encoding(error.to_string())
+    } else {
+        io_error(error)
+    }
+}
+
+/// Classify a failure of the socket. Any but a timeout means
+/// the connection can't be relied on, so is a connection
+/// error, for a reconnect policy to handle.
+fn io_error(error: io::Error) -> RpcError {
+    if is_timeout(&error) {
+        RpcError::Timeout
+    } else {
+        RpcError::Connection(error)
+    }
+}
+
+/// Fail with the error of `request` as a whole, if any, or if
+/// the response doesn't hold a result for each call.
+fn check_error(
+    request: &schema::Request,
+    response: &schema::Response,
+) -> Result<(), RpcError> {
+    if let Some(error) = response.error.as_ref() {
+        return Err(remote_error(request.calls.first(), error));
+    }
+    if response.results.len() != request.calls.len() {
+        return Err(RpcError::Encoding(format!(
+            "{} results for {} calls",
+            response.results.len(),
+            request.calls.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Fail as [`check_error`] does, or with the error of the
+/// first call in `request` that failed, if any.
+fn check_results(
+    request: &schema::Request,
+    response: &schema::Response,
+) -> Result<(), RpcError> {
+    check_error(request, response)?;
+    for (call, result) in request.calls.iter().zip(&response.results) {
+        if let Some(error) = result.error.as_ref() {
+            return Err(remote_error(Some(call), error));
+        }
+    }
+
+    Ok(())
+}
+
+/// The response to a single call from a batch, or its error.
+pub(crate) fn call_response(
+    call: &schema::ProcedureCall,
+    result: &schema::ProcedureResult,
+) -> Result<schema::Response, RpcError> {
+    match result.error.as_ref() {
+        Some(error) => Err(remote_error(Some(call), error)),
+        None => Ok(schema::Response {
+            results: vec![result.clone()],
+            ..Default::default()
+        }),
+    }
+}
+
+fn remote_error(
+    call: Option<&schema::ProcedureCall>,
+    error: &schema::Error,
+) -> RpcError {
+    RpcError::Remote(Box::new(RemoteError {
+        service: call.map(|c| c.service.clone()).unwrap_or_default(),
+        procedure: call.map(|c| c.procedure.clone()).unwrap_or_default(),
+        exception_service: error.service.clone(),
+        name: error.name.clone(),
+        description: error.description.clone(),
+        stack_trace: Some(error.stack_trace.clone())
+            .filter(|stack_trace| !stack_trace.is_empty()),
+    }))
+}
+
 fn send<T: protobuf::Message>(
     rpc: &mut TcpStream,
-    message: T,
+    message: &T,
 ) -> Result<(), RpcError> {
     message
         .write_length_delimited_to_writer(rpc)
-        .map_err(Into::into)
+        .map_err(transport_error)
 }
 
 fn recv<T: protobuf::Message + Default>(
     rpc: &mut TcpStream,
+    limit: usize,
 ) -> Result<T, RpcError> {
-    CodedInputStream::new(rpc)
-        .read_message()
-        .map_err(Into::into)
+    recv_counted(rpc, limit).map(|(message, _)| message)
+}
+
+/// Receive a message, along with the number of bytes read.
+///
+/// Exactly one message is read, so nothing following it in
+/// the socket is lost, such as the next batch of stream
+/// updates.
+///
+/// Fails without reading the message if it is longer than
+/// `limit` bytes.
+fn recv_counted<T: protobuf::Message + Default>(
+    rpc: &mut TcpStream,
+    limit: usize,
+) -> Result<(T, u64), RpcError> {
+    let (message, received) = recv_raw(rpc, limit)?;
+    Ok((T::parse_from_bytes(&message)?, received))
+}
+
+/// Receive a message without parsing it, along with the number
+/// of bytes read, as [`recv_counted`] does.
+fn recv_raw(
+    rpc: &mut TcpStream,
+    limit: usize,
+) -> Result<(Vec<u8>, u64), RpcError> {
+    let (length, prefix) = recv_length(rpc)?;
+    if length > limit {
+        return Err(RpcError::Encoding(format!(
+            "message of {length} bytes exceeds the limit of {limit}"
+        )));
+    }
+    let mut message = vec![0; length];
+    rpc.read_exact(&mut message).map_err(io_error)?;
+    Ok((message, (prefix + length) as u64))
+}
+
+/// Receive the varint length prefixing a message, along with
+/// the number of bytes it took.
+fn recv_length(rpc: &mut TcpStream) -> Result<(usize, usize), RpcError> {
+    let mut length = 0u64;
+    for i in 0..10 {
+        let mut byte = [0];
+        rpc.read_exact(&mut byte).map_err(io_error)?;
+        length |= u64::from(byte[0] & 0x7f) << (7 * i);
+        if byte[0] & 0x80 == 0 {
+            let length = usize::try_from(length).map_err(|_| {
+                RpcError::Encoding(format!("message too long: {length}"))
+            })?;
+            return Ok((length, i + 1));
+        }
+    }
+    Err(RpcError::Encoding("malformed message length".into()))
 }
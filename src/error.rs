@@ -1,4 +1,4 @@
-use std::{io, sync::PoisonError};
+use std::{fmt, io, sync::PoisonError};
 
 use thiserror::Error;
 
@@ -7,10 +7,20 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum RpcError {
     /// `Connection` indicates the client was unable to
-    /// connect to the server.
-    #[error("Connection failed")]
+    /// connect to the server, or lost its connection.
+    #[error("Connection failed: {0}")]
     Connection(#[from] io::Error),
 
+    /// `Handshake` indicates the server rejected the
+    /// connection request.
+    #[error("Handshake failed: {0}")]
+    Handshake(String),
+
+    /// `Timeout` indicates the server did not respond in
+    /// time.
+    #[error("Timed out waiting for the server")]
+    Timeout,
+
     /// `Client` errors capture runtime errors from within
     /// the client.
     #[error("Unexpected client error")]
@@ -21,12 +31,79 @@ pub enum RpcError {
     #[error("Encoding error: {0}")]
     Encoding(String),
 
+    /// `Remote` errors are raised by the server while
+    /// executing a procedure.
+    #[error(transparent)]
+    Remote(Box<RemoteError>),
+
     /// `ProtobufError` indicates an error parsing server
     /// messages.
     #[error(transparent)]
     ProtobufError(#[from] protobuf::Error),
 }
 
+/// The details of an exception thrown by a procedure.
+///
+/// `service` and `procedure` name the call that failed, while
+/// `name` is the type of exception it threw, such as
+/// `InvalidOperationException`, and `exception_service` is the
+/// service defining that type, if any.
+///
+/// It displays as a single line naming the call and exception,
+/// with the server's stack trace following in the alternate
+/// form, `{:#}`:
+///
+/// ```
+/// use krpc_client::error::{RemoteError, RpcError};
+/// let error = RpcError::Remote(Box::new(RemoteError {
+///     service: "SpaceCenter".into(),
+///     procedure: "Vessel_get_Name".into(),
+///     exception_service: String::new(),
+///     name: "InvalidOperationException".into(),
+///     description: "No such vessel".into(),
+///     stack_trace: Some("at Vessel.get_Name ()".into()),
+/// }));
+/// assert_eq!(
+///     error.to_string(),
+///     "SpaceCenter.Vessel_get_Name failed: \
+///      InvalidOperationException: No such vessel",
+/// );
+/// assert_eq!(
+///     format!("{error:#}"),
+///     "SpaceCenter.Vessel_get_Name failed: \
+///      InvalidOperationException: No such vessel\n\
+///      at Vessel.get_Name ()",
+/// );
+/// ```
+#[derive(Error, Debug)]
+pub struct RemoteError {
+    pub service: String,
+    pub procedure: String,
+    pub exception_service: String,
+    pub name: String,
+    pub description: String,
+    pub stack_trace: Option<String>,
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Errors with the request as a whole have no call.
+        if !self.service.is_empty() {
+            write!(f, "{}.{} failed: ", self.service, self.procedure)?;
+        }
+        if !self.name.is_empty() {
+            write!(f, "{}: ", self.name)?;
+        }
+        write!(f, "{}", self.description)?;
+        match &self.stack_trace {
+            Some(stack_trace) if f.alternate() => {
+                write!(f, "\n{stack_trace}")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 impl<T> From<PoisonError<T>> for RpcError {
     fn from(_: PoisonError<T>) -> Self {
         RpcError::Client
@@ -1,29 +1,115 @@
+pub mod batch;
 pub mod client;
 pub mod error;
+pub mod prepared;
 pub mod stream;
+pub mod transport;
+
+pub use client::{Client, ClientHandle};
+pub use schema::{Quaternion, Vector3};
+
+/// The services of the kRPC server, generated from their
+/// definitions.
+///
+/// Services defining exceptions have an error enum of them,
+/// such as [`KRPCError`][krpc_error], which a remote error
+/// converts to with `try_from`.
+///
+/// [krpc_error]: crate::services::krpc::KRPCError
+pub mod services {
+    include!(concat!(env!("OUT_DIR"), "/services/mod.rs"));
+}
 
-pub use client::Client;
+/// The types most programs need, for importing all at once.
+///
+#[cfg_attr(feature = "space_center", doc = "```no_run")]
+#[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
+/// use krpc_client::prelude::*;
+///
+/// let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
+/// let vessel = SpaceCenter::new(client).active_vessel()?;
+/// if vessel.situation()? == VesselSituation::PreLaunch {
+///     vessel.control()?.activate_next_stage()?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub mod prelude {
+    pub use crate::{
+        error::RpcError, services::prelude::*, stream::Stream, Client,
+    };
+}
 
-pub mod services {
-    include!(concat!(env!("OUT_DIR"), "/services.rs"));
+/// The messages of the kRPC protocol, such as those returned
+/// by [`Client::status`] and [`Client::services`], or the
+/// requests sent by [`Client::call`].
+pub mod messages {
+    pub use crate::schema::krpc::*;
 }
 
-mod schema {
+// Public for the generated services, which may be generated
+// into another crate, but not part of the API.
+#[doc(hidden)]
+pub mod schema {
     use std::{
         collections::{HashMap, HashSet},
         hash::Hash,
-        sync::Arc,
     };
 
     pub use krpc::*;
     use protobuf::Message;
 
-    use crate::{client::Client, error::RpcError};
+    use crate::{client::ClientHandle, error::RpcError};
 
     include!(concat!(env!("OUT_DIR"), "/krpc.rs"));
+    /// A vector of three doubles, such as a position or a
+    /// direction, in some reference frame.
+    #[derive(Debug, Default, Copy, Clone, PartialEq)]
+    pub struct Vector3(pub (f64, f64, f64));
+
+    /// A quaternion of four doubles, `(x, y, z, w)`,
+    /// representing a rotation in some reference frame.
+    #[derive(Debug, Default, Copy, Clone, PartialEq)]
+    pub struct Quaternion(pub (f64, f64, f64, f64));
+
+    macro_rules! geometry_type {
+        ($name:ident, $tuple:ty) => {
+            impl From<$tuple> for $name {
+                fn from(tuple: $tuple) -> Self {
+                    Self(tuple)
+                }
+            }
+
+            impl From<$name> for $tuple {
+                fn from(value: $name) -> Self {
+                    value.0
+                }
+            }
+
+            impl DecodeUntagged for $name {
+                fn decode_untagged(
+                    client: ClientHandle,
+                    buf: &[u8],
+                ) -> Result<Self, RpcError> {
+                    <$tuple>::decode_untagged(client, buf).map(Self)
+                }
+            }
+
+            impl EncodeUntagged for $name {
+                fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
+                    self.0.encode_untagged()
+                }
+            }
+        };
+    }
+
+    geometry_type!(Vector3, (f64, f64, f64));
+    geometry_type!(Quaternion, (f64, f64, f64, f64));
+
     pub trait DecodeUntagged: Sized {
         fn decode_untagged(
-            client: Arc<Client>,
+            client: ClientHandle,
             buf: &[u8],
         ) -> Result<Self, RpcError>;
     }
@@ -31,13 +117,13 @@ mod schema {
     pub trait FromResponse: Sized {
         fn from_response(
             response: Response,
-            client: Arc<Client>,
+            client: ClientHandle,
         ) -> Result<Self, RpcError>;
     }
 
     impl DecodeUntagged for () {
         fn decode_untagged(
-            _client: Arc<Client>,
+            _client: ClientHandle,
             _buf: &[u8],
         ) -> Result<Self, RpcError> {
             Ok(())
@@ -47,9 +133,12 @@ mod schema {
     impl<T: DecodeUntagged> FromResponse for T {
         fn from_response(
             response: Response,
-            client: Arc<Client>,
+            client: ClientHandle,
         ) -> Result<T, RpcError> {
-            Self::decode_untagged(client, &response.results[0].value)
+            let result = response.results.first().ok_or_else(|| {
+                RpcError::Encoding("response has no results".into())
+            })?;
+            Self::decode_untagged(client, &result.value)
         }
     }
 
@@ -71,7 +160,7 @@ mod schema {
         }
     }
 
-    impl<T: EncodeUntagged> EncodeUntagged for &T {
+    impl<T: EncodeUntagged + ?Sized> EncodeUntagged for &T {
         fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
             (*self).encode_untagged()
         }
@@ -97,65 +186,325 @@ mod schema {
         };
     }
 
+    // The macros below are used by the generated services,
+    // which may live in another crate, so they name everything
+    // through `$crate`.
+
+    /// Define a class of server-side object.
+    ///
+    /// Each class is its own type, even though the server
+    /// identifies every instance by a number, so one class can't
+    /// be passed where another is expected:
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```compile_fail")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{
+    ///     services::space_center::SpaceCenter, transport::MockClient,
+    /// };
+    /// let space_center = SpaceCenter::new(MockClient::new().client());
+    /// let part = space_center.active_vessel()?.parts()?.root()?;
+    /// space_center.set_active_vessel(&part)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[doc(hidden)]
+    #[macro_export]
     macro_rules! rpc_object {
         ($name:ident) => {
+            #[derive(Clone)]
             pub struct $name {
                 id: u64,
-                client: ::std::sync::Arc<crate::client::Client>,
+                client: $crate::ClientHandle,
+            }
+
+            impl $name {
+                /// The ID of the server-side object this refers to.
+                pub fn object_id(&self) -> u64 {
+                    self.id
+                }
+            }
+
+            impl ::std::fmt::Debug for $name {
+                fn fmt(
+                    &self,
+                    f: &mut ::std::fmt::Formatter<'_>,
+                ) -> ::std::fmt::Result {
+                    f.debug_tuple(stringify!($name)).field(&self.id).finish()
+                }
+            }
+
+            // Objects are the same if they refer to the same
+            // server-side object over the same connection.
+            impl PartialEq for $name {
+                fn eq(&self, other: &Self) -> bool {
+                    self.id == other.id
+                        && $crate::ClientHandle::ptr_eq(&self.client, &other.client)
+                }
             }
 
-            impl crate::schema::DecodeUntagged for $name {
+            impl Eq for $name {}
+
+            impl ::std::hash::Hash for $name {
+                fn hash<H: ::std::hash::Hasher>(&self, state: &mut H) {
+                    ::std::hash::Hash::hash(&self.id, state);
+                }
+            }
+
+            impl $crate::schema::DecodeUntagged for $name {
                 fn decode_untagged(
-                    client: ::std::sync::Arc<crate::client::Client>,
+                    client: $crate::ClientHandle,
                     buf: &[u8],
-                ) -> Result<Self, RpcError> {
+                ) -> Result<Self, $crate::error::RpcError> {
                     Ok($name {
-                        id: u64::decode_untagged(client.clone(), buf)?,
+                        id: <u64 as $crate::schema::DecodeUntagged>::decode_untagged(
+                            client.clone(),
+                            buf,
+                        )?,
                         client,
                     })
                 }
             }
 
-            impl crate::schema::EncodeUntagged for $name {
-                fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
-                    self.id.encode_untagged()
+            impl $crate::schema::EncodeUntagged for $name {
+                fn encode_untagged(
+                    &self,
+                ) -> Result<Vec<u8>, $crate::error::RpcError> {
+                    $crate::schema::EncodeUntagged::encode_untagged(&self.id)
                 }
             }
         };
     }
 
+    #[doc(hidden)]
+    #[macro_export]
     macro_rules! rpc_enum {
-        ($name:ident, [$($value:ident),+$(,)?]) => {
+        ($name:ident, [$($value:ident = $discriminant:literal),+$(,)?]) => {
             #[derive(Debug, Copy, Clone, Eq, PartialEq)]
+            #[repr(i32)]
             pub enum $name {$(
-                $value,
+                $value = $discriminant,
             )+}
 
-            impl crate::schema::DecodeUntagged for $name {
+            impl TryFrom<i32> for $name {
+                type Error = $crate::error::RpcError;
+
+                fn try_from(
+                    value: i32,
+                ) -> Result<Self, $crate::error::RpcError> {
+                    match value {
+                        $($discriminant => Ok($name::$value),)+
+                        _ => Err($crate::error::RpcError::Encoding(format!(
+                            "invalid {} variant: {value}",
+                            stringify!($name),
+                        ))),
+                    }
+                }
+            }
+
+            impl $crate::schema::DecodeUntagged for $name {
                 fn decode_untagged(
-                    client: ::std::sync::Arc<crate::client::Client>,
+                    client: $crate::ClientHandle,
                     buf: &[u8]
-                ) -> Result<Self, RpcError> {
-                    match i32::decode_untagged(client, buf)? {
-                        $(i if i == $name::$value as i32 => Ok($name::$value),)+
-                        _ => Err(RpcError::Encoding("invalid enum variant".into()))
-                    }
+                ) -> Result<Self, $crate::error::RpcError> {
+                    <i32 as $crate::schema::DecodeUntagged>::decode_untagged(
+                        client,
+                        buf,
+                    )?
+                    .try_into()
                 }
             }
 
-            impl crate::schema::EncodeUntagged for $name {
-                fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
-                    (*self as i32).encode_untagged()
+            impl $crate::schema::EncodeUntagged for $name {
+                fn encode_untagged(
+                    &self,
+                ) -> Result<Vec<u8>, $crate::error::RpcError> {
+                    $crate::schema::EncodeUntagged::encode_untagged(
+                        &(*self as i32),
+                    )
                 }
             }
+
+            $crate::rpc_enum_serde!($name, [$($value),+]);
         }
     }
 
+    // Whether enums are serializable is up to this crate's
+    // features, not those of the crate generating them.
+
+    /// Serialize an enum by variant name, as derived
+    /// implementations would for unit variants.
+    #[cfg(feature = "serde")]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! rpc_enum_serde {
+        ($name:ident, [$($value:ident),+]) => {
+            impl $crate::schema::serde::Serialize for $name {
+                fn serialize<S: $crate::schema::serde::Serializer>(
+                    &self,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(match self {
+                        $($name::$value => stringify!($value),)+
+                    })
+                }
+            }
+
+            impl<'de> $crate::schema::serde::Deserialize<'de> for $name {
+                fn deserialize<D: $crate::schema::serde::Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<Self, D::Error> {
+                    let name = <::std::borrow::Cow<'de, str> as
+                        $crate::schema::serde::Deserialize<'de>>::deserialize(
+                        deserializer,
+                    )?;
+                    match name.as_ref() {
+                        $(stringify!($value) => Ok($name::$value),)+
+                        _ => Err(<D::Error as $crate::schema::serde::de::Error>::unknown_variant(
+                            &name,
+                            &[$(stringify!($value)),+],
+                        )),
+                    }
+                }
+            }
+        };
+    }
+
+    #[cfg(not(feature = "serde"))]
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! rpc_enum_serde {
+        ($($tokens:tt)*) => {};
+    }
+
+    /// A struct naming the elements of a tuple returned by a
+    /// procedure, converting to and from the tuple.
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! rpc_tuple {
+        ($(#[$attr:meta])* $name:ident, [$($field:ident: $ty:ty),+$(,)?]) => {
+            $(#[$attr])*
+            #[derive(Debug, Clone, PartialEq)]
+            pub struct $name {$(
+                pub $field: $ty,
+            )+}
+
+            impl From<($($ty,)+)> for $name {
+                fn from(($($field,)+): ($($ty,)+)) -> Self {
+                    Self {$($field,)+}
+                }
+            }
+
+            impl From<$name> for ($($ty,)+) {
+                fn from(value: $name) -> Self {
+                    ($(value.$field,)+)
+                }
+            }
+
+            impl $crate::schema::DecodeUntagged for $name {
+                fn decode_untagged(
+                    client: $crate::ClientHandle,
+                    buf: &[u8],
+                ) -> Result<Self, $crate::error::RpcError> {
+                    <($($ty,)+) as $crate::schema::DecodeUntagged>::decode_untagged(
+                        client,
+                        buf,
+                    )
+                    .map(Self::from)
+                }
+            }
+
+            impl $crate::schema::EncodeUntagged for $name {
+                fn encode_untagged(
+                    &self,
+                ) -> Result<Vec<u8>, $crate::error::RpcError> {
+                    $crate::schema::EncodeUntagged::encode_untagged(
+                        &($(&self.$field,)+),
+                    )
+                }
+            }
+        };
+    }
+
+    /// An enum of the exceptions a service defines, each holding
+    /// the details of a [`RemoteError`][crate::error::RemoteError]
+    /// that threw it.
+    #[doc(hidden)]
+    #[macro_export]
+    macro_rules! rpc_error {
+        (
+            $name:ident,
+            $service:literal,
+            [$($(#[$attr:meta])* $variant:ident = $exception:literal),+$(,)?]
+        ) => {
+            #[doc = concat!(" An exception defined by the `", $service, "` service.")]
+            ///
+            /// Convert an [`RpcError`] with [`TryFrom`] to match on
+            /// the exception, which gives the error back unchanged
+            /// if it is some other kind.
+            #[derive(Debug)]
+            pub enum $name {$(
+                $(#[$attr])*
+                $variant(Box<$crate::error::RemoteError>),
+            )+}
+
+            impl $name {
+                /// The details of the exception.
+                pub fn remote_error(&self) -> &$crate::error::RemoteError {
+                    match self {
+                        $($name::$variant(error) => error,)+
+                    }
+                }
+            }
+
+            impl ::std::fmt::Display for $name {
+                fn fmt(
+                    &self,
+                    f: &mut ::std::fmt::Formatter<'_>,
+                ) -> ::std::fmt::Result {
+                    ::std::fmt::Display::fmt(self.remote_error(), f)
+                }
+            }
+
+            impl ::std::error::Error for $name {}
+
+            impl TryFrom<$crate::error::RpcError> for $name {
+                type Error = $crate::error::RpcError;
+
+                fn try_from(
+                    error: $crate::error::RpcError,
+                ) -> Result<Self, $crate::error::RpcError> {
+                    match error {
+                        $crate::error::RpcError::Remote(remote)
+                            if remote.exception_service == $service =>
+                        {
+                            match remote.name.as_str() {
+                                $($exception => Ok($name::$variant(remote)),)+
+                                _ => Err($crate::error::RpcError::Remote(remote)),
+                            }
+                        }
+                        error => Err(error),
+                    }
+                }
+            }
+
+            impl From<$name> for $crate::error::RpcError {
+                fn from(error: $name) -> Self {
+                    match error {
+                        $($name::$variant(remote) => {
+                            $crate::error::RpcError::Remote(remote)
+                        })+
+                    }
+                }
+            }
+        };
+    }
+
     macro_rules! decode_untagged {
         ($to:ty, $proto:ident) => {
             impl DecodeUntagged for $to {
                 fn decode_untagged(
-                    _: ::std::sync::Arc<crate::client::Client>,
+                    _: crate::ClientHandle,
                     b: &[u8],
                 ) -> Result<Self, RpcError> {
                     ::protobuf::CodedInputStream::from_bytes(b)
@@ -170,7 +519,7 @@ mod schema {
         ($($m:ty),+$(,)?) => {$(
             impl DecodeUntagged for $m {
                 fn decode_untagged(
-                    _: ::std::sync::Arc<crate::client::Client>,
+                    _: crate::ClientHandle,
                     b: &[u8]
                 ) -> Result<Self, RpcError> {
                     Self::parse_from_bytes(&b[..]).map_err(|e| RpcError::from(e))
@@ -196,164 +545,49 @@ mod schema {
         }
     }
 
-    impl<T0, T1> DecodeUntagged for (T0, T1)
-    where
-        T0: DecodeUntagged,
-        T1: DecodeUntagged,
-    {
-        fn decode_untagged(
-            client: Arc<Client>,
-            buf: &[u8],
-        ) -> Result<Self, RpcError> {
-            let tuple = Tuple::decode_untagged(client.clone(), buf)?;
-            Ok((
-                T0::decode_untagged(
-                    client.clone(),
-                    tuple.items.get(0).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-                T1::decode_untagged(
-                    client,
-                    tuple.items.get(1).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-            ))
-        }
-    }
-
-    impl<T0, T1> EncodeUntagged for (T0, T1)
-    where
-        T0: EncodeUntagged,
-        T1: EncodeUntagged,
-    {
-        fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
-            Tuple {
-                items: vec![
-                    self.0.encode_untagged()?,
-                    self.1.encode_untagged()?,
-                ],
-                ..Default::default()
+    macro_rules! tuple_untagged {
+        ($($item:ident: $index:tt),+) => {
+            impl<$($item),+> DecodeUntagged for ($($item,)+)
+            where
+                $($item: DecodeUntagged,)+
+            {
+                fn decode_untagged(
+                    client: ClientHandle,
+                    buf: &[u8],
+                ) -> Result<Self, RpcError> {
+                    let tuple = Tuple::decode_untagged(client.clone(), buf)?;
+                    let mut items = tuple.items.iter();
+                    Ok(($(
+                        $item::decode_untagged(
+                            client.clone(),
+                            items.next().ok_or(RpcError::Encoding(
+                                "tuple element out of range".into(),
+                            ))?,
+                        )?,
+                    )+))
+                }
             }
-            .encode_untagged()
-        }
-    }
-
-    impl<T0, T1, T2> DecodeUntagged for (T0, T1, T2)
-    where
-        T0: DecodeUntagged,
-        T1: DecodeUntagged,
-        T2: DecodeUntagged,
-    {
-        fn decode_untagged(
-            client: Arc<Client>,
-            buf: &[u8],
-        ) -> Result<Self, RpcError> {
-            let tuple = Tuple::decode_untagged(client.clone(), buf)?;
-            Ok((
-                T0::decode_untagged(
-                    client.clone(),
-                    tuple.items.get(0).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-                T1::decode_untagged(
-                    client.clone(),
-                    tuple.items.get(1).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-                T2::decode_untagged(
-                    client,
-                    tuple.items.get(2).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-            ))
-        }
-    }
 
-    impl<T0, T1, T2> EncodeUntagged for (T0, T1, T2)
-    where
-        T0: EncodeUntagged,
-        T1: EncodeUntagged,
-        T2: EncodeUntagged,
-    {
-        fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
-            Tuple {
-                items: vec![
-                    self.0.encode_untagged()?,
-                    self.1.encode_untagged()?,
-                    self.2.encode_untagged()?,
-                ],
-                ..Default::default()
+            impl<$($item),+> EncodeUntagged for ($($item,)+)
+            where
+                $($item: EncodeUntagged,)+
+            {
+                fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
+                    Tuple {
+                        items: vec![$(self.$index.encode_untagged()?),+],
+                        ..Default::default()
+                    }
+                    .encode_untagged()
+                }
             }
-            .encode_untagged()
-        }
-    }
-
-    impl<T0, T1, T2, T3> DecodeUntagged for (T0, T1, T2, T3)
-    where
-        T0: DecodeUntagged,
-        T1: DecodeUntagged,
-        T2: DecodeUntagged,
-        T3: DecodeUntagged,
-    {
-        fn decode_untagged(
-            client: Arc<Client>,
-            buf: &[u8],
-        ) -> Result<Self, RpcError> {
-            let tuple = Tuple::decode_untagged(client.clone(), buf)?;
-            Ok((
-                T0::decode_untagged(
-                    client.clone(),
-                    tuple.items.get(0).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-                T1::decode_untagged(
-                    client.clone(),
-                    tuple.items.get(1).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-                T2::decode_untagged(
-                    client.clone(),
-                    tuple.items.get(2).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-                T3::decode_untagged(
-                    client,
-                    tuple.items.get(3).ok_or(RpcError::Encoding(
-                        "tuple element out of range".into(),
-                    ))?,
-                )?,
-            ))
-        }
+        };
     }
 
-    impl<T0, T1, T2, T3> EncodeUntagged for (T0, T1, T2, T3)
-    where
-        T0: EncodeUntagged,
-        T1: EncodeUntagged,
-        T2: EncodeUntagged,
-        T3: EncodeUntagged,
-    {
-        fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
-            Tuple {
-                items: vec![
-                    self.0.encode_untagged()?,
-                    self.1.encode_untagged()?,
-                    self.2.encode_untagged()?,
-                    self.3.encode_untagged()?,
-                ],
-                ..Default::default()
-            }
-            .encode_untagged()
-        }
-    }
+    // Keep in step with `MAX_TUPLE_ARITY` in krpc_build.
+    tuple_untagged!(T0: 0, T1: 1);
+    tuple_untagged!(T0: 0, T1: 1, T2: 2);
+    tuple_untagged!(T0: 0, T1: 1, T2: 2, T3: 3);
+    tuple_untagged!(T0: 0, T1: 1, T2: 2, T3: 3, T4: 4);
 
     impl<K, V> DecodeUntagged for HashMap<K, V>
     where
@@ -361,7 +595,7 @@ mod schema {
         V: DecodeUntagged,
     {
         fn decode_untagged(
-            client: Arc<Client>,
+            client: ClientHandle,
             buf: &[u8],
         ) -> Result<Self, RpcError> {
             let mut map: HashMap<K, V> = HashMap::new();
@@ -405,7 +639,7 @@ mod schema {
         T: DecodeUntagged + Eq + Hash,
     {
         fn decode_untagged(
-            client: Arc<Client>,
+            client: ClientHandle,
             buf: &[u8],
         ) -> Result<Self, RpcError> {
             let protoset = Set::decode_untagged(client.clone(), buf)?;
@@ -442,7 +676,7 @@ mod schema {
         T: DecodeUntagged,
     {
         fn decode_untagged(
-            client: Arc<Client>,
+            client: ClientHandle,
             buf: &[u8],
         ) -> Result<Self, RpcError> {
             let mut v = Vec::new();
@@ -476,6 +710,12 @@ mod schema {
     }
 
     impl EncodeUntagged for String {
+        fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
+            self.as_str().encode_untagged()
+        }
+    }
+
+    impl EncodeUntagged for str {
         fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
             let mut buf: Vec<u8> = Vec::new();
             {
@@ -488,9 +728,22 @@ mod schema {
         }
     }
 
+    impl EncodeUntagged for Vec<u8> {
+        fn encode_untagged(&self) -> Result<Vec<u8>, RpcError> {
+            let mut buf: Vec<u8> = Vec::new();
+            {
+                let mut os = protobuf::CodedOutputStream::new(&mut buf);
+                os.write_bytes_no_tag(self).map_err(RpcError::from)?;
+                os.flush().map_err(RpcError::from)?;
+            }
+
+            Ok(buf)
+        }
+    }
+
     impl<T: DecodeUntagged> DecodeUntagged for Option<T> {
         fn decode_untagged(
-            client: Arc<Client>,
+            client: ClientHandle,
             buf: &[u8],
         ) -> Result<Self, RpcError> {
             if buf == [0u8] {
@@ -515,6 +768,7 @@ mod schema {
     decode_untagged!(f32, read_float);
     decode_untagged!(f64, read_double);
     decode_untagged!(i32, read_sint32);
+    decode_untagged!(i64, read_sint64);
     decode_untagged!(u32, read_uint32);
     decode_untagged!(u64, read_uint64);
     decode_untagged!(Vec<u8>, read_bytes);
@@ -534,13 +788,16 @@ mod schema {
 
     encode_untagged!(bool, write_bool_no_tag);
     encode_untagged!(i32, write_sint32_no_tag);
+    encode_untagged!(i64, write_sint64_no_tag);
     encode_untagged!(u32, write_uint32_no_tag);
     encode_untagged!(f32, write_float_no_tag);
     encode_untagged!(f64, write_double_no_tag);
     encode_untagged!(u64, write_uint64_no_tag);
 
-    pub(crate) use rpc_enum;
-    pub(crate) use rpc_object;
+    #[cfg(feature = "serde")]
+    pub use ::serde;
+
+    pub use crate::{rpc_enum, rpc_error, rpc_object, rpc_tuple};
 }
 
 pub trait RpcType: schema::DecodeUntagged {}
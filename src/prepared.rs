@@ -0,0 +1,66 @@
+use std::marker::PhantomData;
+
+use protobuf::Message;
+
+use crate::{
+    client::ClientHandle,
+    error::RpcError,
+    schema::{FromResponse, ProcedureCall, Request},
+    RpcType,
+};
+
+/// A procedure call whose request is encoded once, for calls
+/// made repeatedly with the same arguments, such as in a
+/// control loop.
+///
+/// Created with [`Client::prepare`][prepare], using the `_call()`
+/// variant of any procedure. `T` must be the return type of
+/// the procedure being called.
+///
+#[cfg_attr(feature = "space_center", doc = "```no_run")]
+#[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
+/// use krpc_client::{services::space_center::SpaceCenter, Client};
+/// # let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
+/// let space_center = SpaceCenter::new(client.clone());
+///
+/// let ut = client.prepare::<f64>(space_center.ut_call()?)?;
+/// for _ in 0..10 {
+///     println!("It's {} o'clock", ut.call()?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [prepare]: crate::Client::prepare
+pub struct PreparedCall<T: RpcType> {
+    client: ClientHandle,
+    request: Request,
+    encoded: Vec<u8>,
+    phantom: PhantomData<T>,
+}
+
+impl<T: RpcType> PreparedCall<T> {
+    /// Constructs a new `PreparedCall`, encoding its request.
+    pub fn new(
+        client: ClientHandle,
+        call: ProcedureCall,
+    ) -> Result<Self, RpcError> {
+        let request = Request::from(call);
+        let encoded = request.write_length_delimited_to_bytes()?;
+        Ok(Self {
+            client,
+            request,
+            encoded,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Make the call, decoding its result.
+    pub fn call(&self) -> Result<T, RpcError> {
+        let response = self
+            .client
+            .call_encoded(&self.request, Some(&self.encoded))?;
+        T::from_response(response, self.client.clone())
+    }
+}
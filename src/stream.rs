@@ -1,13 +1,21 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    io,
     marker::PhantomData,
-    sync::{Arc, Condvar, Mutex},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Condvar, Mutex, PoisonError,
+    },
+    time::{Duration, Instant},
 };
 
 use crate::{
-    client::Client,
+    client::ClientHandle,
     error::RpcError,
-    schema::{DecodeUntagged, ProcedureCall, ProcedureResult},
+    schema::{
+        self, DecodeUntagged, EncodeUntagged, ProcedureCall, ProcedureResult,
+        StreamUpdate,
+    },
     services::krpc::KRPC,
     RpcType,
 };
@@ -15,7 +23,8 @@ use crate::{
 /// A streaming procedure call.
 ///
 /// `Stream<T>` is created by calling any procedure with the
-/// `_stream()` suffix. This will start the stream
+/// `_stream()` suffix, or by passing any procedure call to
+/// [`Client::add_stream`][add_stream]. This will start the stream
 /// automatically.
 ///
 /// This type provides access to the procedure's
@@ -26,87 +35,484 @@ use crate::{
 ///
 /// The stream will attempt to remove itself when dropped.
 /// Otherwise the server will remove remaining streams when
-/// the client disconnects.
+/// the client disconnects. The server returns the same
+/// stream for the same procedure call, so it is only removed
+/// once every `Stream` of that call has been dropped.
+///
+#[cfg_attr(feature = "space_center", doc = "```")]
+#[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
+/// use krpc_client::{
+///     services::space_center::SpaceCenter, transport::MockClient,
+/// };
+/// let mock = MockClient::new();
+/// mock.respond("SpaceCenter", "get_UT", 0.0)?;
+/// let space_center = SpaceCenter::new(mock.client());
+///
+/// let ut = space_center.ut_stream()?;
+/// let also_ut = space_center.ut_stream()?;
+/// drop(ut);
+/// assert!(!mock.called("KRPC", "RemoveStream"));
+/// drop(also_ut);
+/// assert!(mock.called("KRPC", "RemoveStream"));
+/// # Ok(())
+/// # }
+/// ```
 ///
 /// [wait]: Stream::wait
 /// [set_rate]: Stream::set_rate
 /// [get]: Stream::get
+/// [add_stream]: crate::Client::add_stream
 pub struct Stream<T: RpcType> {
-    pub(crate) id: u64,
+    key: StreamKey,
+    /// The server-side streams providing the values.
+    ids: Vec<u64>,
     krpc: KRPC,
-    client: Arc<Client>,
+    client: ClientHandle,
     phantom: PhantomData<T>,
 }
 
-type StreamEntry = Arc<(Mutex<ProcedureResult>, Condvar)>;
+/// A condition evaluated by the server, such as one created
+/// with [`KRPC::add_event`].
+///
+/// An event is backed by a stream of booleans that becomes
+/// `true` once the condition is met. The stream starts when
+/// the event is first waited on, and an event that has
+/// already fired is seen immediately.
+///
+#[cfg_attr(feature = "space_center", doc = "```no_run")]
+#[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
+/// use std::time::Duration;
+///
+/// use krpc_client::{
+///     services::{krpc::KRPC, space_center::SpaceCenter},
+///     Client,
+/// };
+/// # let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
+/// let krpc = KRPC::new(client.clone());
+/// let space_center = SpaceCenter::new(client);
+///
+/// // Fire once another minute of game time has passed.
+/// let ut = krpc.expression_call(space_center.ut_call()?)?;
+/// let later = krpc.expression_constant_double(space_center.ut()? + 60.0)?;
+/// let event = krpc.add_event(&krpc.expression_greater_than(&ut, &later)?)?;
+/// if !event.wait_timeout(Duration::from_secs(120))? {
+///     println!("Is the game paused?");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct Event {
+    stream: Stream<bool>,
+    started: AtomicBool,
+}
+
+/// Identifies the values of a [`Stream`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum StreamKey {
+    /// A stream on the server, by its ID.
+    Server(u64),
+    /// Several server streams combined into a tuple, by an ID
+    /// assigned by the client.
+    Combined(u64),
+}
+
+type StreamEntry = Arc<(Mutex<Latest>, Condvar)>;
+
+/// A stream's latest result, or `None` before its first
+/// update, and how many updates it has received.
+#[derive(Default)]
+struct Latest {
+    result: Option<ProcedureResult>,
+    updates: u64,
+}
+
 #[derive(Default)]
 pub(crate) struct StreamWrangler {
-    streams: Mutex<HashMap<u64, StreamEntry>>,
+    streams: Mutex<HashMap<StreamKey, StreamEntry>>,
+    /// The number of `Stream`s using each server-side stream.
+    /// The server returns the same stream for the same call,
+    /// so several may share one.
+    handles: Mutex<HashMap<u64, usize>>,
+    /// The server-side streams making up each combined stream.
+    combined: Mutex<HashMap<u64, Vec<u64>>>,
+    next_combined: AtomicU64,
+    connection: Mutex<Connection>,
+}
+
+/// The connection delivering stream updates. Each new one
+/// replaces the last, so a closing connection only closes the
+/// streams if nothing has replaced it.
+#[derive(Default)]
+struct Connection {
+    generation: u64,
+    closed: bool,
 }
 
 impl StreamWrangler {
+    /// Start receiving updates from a new connection, returning
+    /// its generation for [`close`][StreamWrangler::close].
+    pub fn open(&self) -> u64 {
+        let mut connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        connection.generation += 1;
+        connection.closed = false;
+        connection.generation
+    }
+
+    /// Stop receiving updates from the connection `generation`,
+    /// failing anything waiting for one, unless a newer
+    /// connection has replaced it.
+    pub fn close(&self, generation: u64) {
+        {
+            let mut connection = self
+                .connection
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if connection.generation != generation {
+                return;
+            }
+            connection.closed = true;
+        }
+        let entries = self
+            .streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for entry in entries {
+            // Take the lock, so that no waiter is between
+            // checking for the connection and waiting.
+            let _latest =
+                entry.0.lock().unwrap_or_else(PoisonError::into_inner);
+            entry.1.notify_all();
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        self.connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .closed
+    }
+
     pub fn insert(
         &self,
-        id: u64,
+        key: StreamKey,
         procedure_result: ProcedureResult,
     ) -> Result<(), RpcError> {
         let mut map = self.streams.lock().unwrap();
-        let (lock, cvar) =
-            { &*map.entry(id).or_insert_with(Default::default).clone() };
+        let (lock, cvar) = { &*map.entry(key).or_default().clone() };
+
+        let mut latest = lock.lock().unwrap();
+        latest.result = Some(procedure_result);
+        latest.updates += 1;
+        cvar.notify_all();
+
+        Ok(())
+    }
+
+    /// Store every result in a batch of stream updates, then
+    /// update the combined streams using any of them.
+    pub fn update(&self, update: StreamUpdate) -> Result<(), RpcError> {
+        let mut updated = HashSet::new();
+        for result in update.results {
+            if let Some(procedure_result) = result.result.into_option() {
+                self.insert(StreamKey::Server(result.id), procedure_result)?;
+                updated.insert(result.id);
+            }
+        }
+
+        let combined = self.combined.lock()?;
+        for (id, parts) in combined.iter() {
+            if parts.iter().any(|part| updated.contains(part)) {
+                self.combine(*id, parts)?;
+            }
+        }
+        Ok(())
+    }
 
-        *lock.lock().unwrap() = procedure_result;
-        cvar.notify_one();
+    /// Track a combined stream of the server-side streams
+    /// `parts`, returning its ID.
+    pub fn add_combined(&self, parts: Vec<u64>) -> Result<u64, RpcError> {
+        let id = self.next_combined.fetch_add(1, Ordering::Relaxed);
+        // Hold the lock while combining, so no update is missed.
+        let mut combined = self.combined.lock()?;
+        self.combine(id, &parts)?;
+        combined.insert(id, parts);
+        Ok(id)
+    }
 
+    pub fn remove_combined(&self, id: u64) -> Result<(), RpcError> {
+        self.combined.lock()?.remove(&id);
+        self.streams.lock()?.remove(&StreamKey::Combined(id));
         Ok(())
     }
 
-    pub fn wait(&self, id: u64) {
+    /// Update a combined stream with the latest value of each
+    /// of its parts, once they all have one.
+    fn combine(&self, id: u64, parts: &[u64]) -> Result<(), RpcError> {
+        let mut items = Vec::with_capacity(parts.len());
+        for part in parts {
+            let entry =
+                self.streams.lock()?.get(&StreamKey::Server(*part)).cloned();
+            let result =
+                entry.and_then(|entry| entry.0.lock().ok()?.result.clone());
+            match result {
+                Some(result) => items.push(result.value),
+                None => return Ok(()),
+            }
+        }
+
+        let tuple = schema::Tuple {
+            items,
+            ..Default::default()
+        };
+        self.insert(
+            StreamKey::Combined(id),
+            ProcedureResult {
+                value: tuple.encode_untagged()?,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Block until the stream's latest value satisfies
+    /// `condition`, or `timeout` elapses. Returns the value if
+    /// the condition was met, and fails if it can't be because
+    /// the connection has closed.
+    pub fn wait_until<T: DecodeUntagged>(
+        &self,
+        client: ClientHandle,
+        key: StreamKey,
+        timeout: Option<Duration>,
+        condition: impl Fn(&T) -> bool,
+    ) -> Result<Option<T>, RpcError> {
+        Ok(self
+            .wait_for(client, key, 0, timeout, condition)?
+            .map(|(value, _)| value))
+    }
+
+    /// Block until the stream has received more than `seen`
+    /// updates, or `timeout` elapses. Returns the latest value
+    /// and the number of updates so far, failing once the
+    /// connection has closed.
+    pub fn wait_newer<T: DecodeUntagged>(
+        &self,
+        client: ClientHandle,
+        key: StreamKey,
+        seen: u64,
+        timeout: Option<Duration>,
+    ) -> Result<Option<(T, u64)>, RpcError> {
+        self.wait_for(client, key, seen, timeout, |_| true)
+    }
+
+    fn wait_for<T: DecodeUntagged>(
+        &self,
+        client: ClientHandle,
+        key: StreamKey,
+        seen: u64,
+        timeout: Option<Duration>,
+        condition: impl Fn(&T) -> bool,
+    ) -> Result<Option<(T, u64)>, RpcError> {
+        let entry = self.streams.lock()?.entry(key).or_default().clone();
+        let (lock, cvar) = &*entry;
+        // A timeout too long to represent never passes.
+        let deadline =
+            timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+
+        // The value is checked and waited on under the same
+        // lock, so an update can't be missed in between.
+        let mut latest = lock.lock()?;
+        loop {
+            if let Some(result) = latest.result.as_ref() {
+                if latest.updates > seen {
+                    let value =
+                        T::decode_untagged(client.clone(), &result.value)?;
+                    if condition(&value) {
+                        return Ok(Some((value, latest.updates)));
+                    }
+                }
+            }
+            if self.is_closed() {
+                return Err(RpcError::Connection(
+                    io::ErrorKind::NotConnected.into(),
+                ));
+            }
+            latest = match deadline {
+                None => cvar.wait(latest)?,
+                Some(deadline) => {
+                    let remaining =
+                        deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Ok(None);
+                    }
+                    cvar.wait_timeout(latest, remaining)?.0
+                }
+            };
+        }
+    }
+
+    pub fn wait(&self, key: StreamKey) {
         let (lock, cvar) = {
             let mut map = self.streams.lock().unwrap();
-            &*map.entry(id).or_insert_with(Default::default).clone()
+            &*map.entry(key).or_default().clone()
         };
-        let result = lock.lock().unwrap();
-        let _result = cvar.wait(result).unwrap();
+        let latest = lock.lock().unwrap();
+        let seen = latest.updates;
+        let _latest = cvar
+            .wait_while(latest, |latest| {
+                latest.updates == seen && !self.is_closed()
+            })
+            .unwrap();
     }
 
-    pub fn remove(&self, id: u64) {
-        let mut map = self.streams.lock().unwrap();
-        map.remove(&id);
+    /// Count a new handle to a stream.
+    pub fn acquire(&self, id: u64) -> Result<(), RpcError> {
+        *self.handles.lock()?.entry(id).or_default() += 1;
+        Ok(())
     }
 
-    pub fn get<T: DecodeUntagged>(
-        &self,
-        client: Arc<Client>,
-        id: u64,
-    ) -> Result<T, RpcError> {
-        let mut map = self.streams.lock().unwrap();
-        let (lock, _) =
-            { &*map.entry(id).or_insert_with(Default::default).clone() };
-        let result = lock.lock().unwrap();
-        T::decode_untagged(client, &result.value)
+    /// Release a handle to a stream, forgetting its value once
+    /// none remain. Returns whether the server-side stream
+    /// should now be removed.
+    pub fn release(&self, id: u64) -> Result<bool, RpcError> {
+        let mut handles = self.handles.lock()?;
+        let remove = match handles.get_mut(&id) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                return Ok(false);
+            }
+            Some(_) => handles.remove(&id).is_some(),
+            // Already removed by `take_all`.
+            None => false,
+        };
+        self.streams.lock()?.remove(&StreamKey::Server(id));
+        Ok(remove)
+    }
+
+    /// Forget the handles to every stream, returning the
+    /// streams to remove from the server. Their last values
+    /// remain readable.
+    pub fn take_all(&self) -> Result<Vec<u64>, RpcError> {
+        Ok(self.handles.lock()?.drain().map(|(id, _)| id).collect())
     }
 }
 
 impl<T: RpcType> Stream<T> {
     pub(crate) fn new(
-        client: Arc<Client>,
+        client: ClientHandle,
         call: ProcedureCall,
     ) -> Result<Self, RpcError> {
+        client.check_streams()?;
         let krpc = KRPC::new(client.clone());
-        let stream = krpc.add_stream(call, true)?;
-        client.await_stream(stream.id);
+        let id = krpc.add_stream(call, Some(true))?.id;
+        let stream = Self::from_id(client, id)?;
+        // Wait for the first value, which may already be here.
+        stream.latest(None)?;
 
-        Ok(Self {
-            id: stream.id,
+        Ok(stream)
+    }
+
+    /// Stream several procedure calls as one tuple.
+    pub(crate) fn combined(
+        client: ClientHandle,
+        calls: Vec<ProcedureCall>,
+    ) -> Result<Self, RpcError> {
+        client.check_streams()?;
+        if calls.is_empty() {
+            return Err(RpcError::Encoding("no calls to stream".into()));
+        }
+
+        let krpc = KRPC::new(client.clone());
+        let mut ids = Vec::with_capacity(calls.len());
+        let added = calls.into_iter().try_for_each(|call| {
+            let id = krpc.add_stream(call, Some(true))?.id;
+            client.acquire_stream(id)?;
+            ids.push(id);
+            Ok(())
+        });
+        let combined = added.and_then(|()| client.combine_streams(ids.clone()));
+        let id = match combined {
+            Ok(id) => id,
+            Err(e) => {
+                for id in ids {
+                    client.remove_stream(id).ok();
+                }
+                return Err(e);
+            }
+        };
+
+        let stream = Self {
+            key: StreamKey::Combined(id),
+            ids,
             krpc,
             client,
             phantom: PhantomData,
+        };
+        stream.latest(None)?;
+
+        Ok(stream)
+    }
+
+    /// Wrap a stream that already exists on the server.
+    pub(crate) fn from_id(
+        client: ClientHandle,
+        id: u64,
+    ) -> Result<Self, RpcError> {
+        client.acquire_stream(id)?;
+        Ok(Self {
+            key: StreamKey::Server(id),
+            ids: vec![id],
+            krpc: KRPC::new(client.clone()),
+            client,
+            phantom: PhantomData,
         })
     }
 
-    /// Set the update rate for this streaming procedure.
+    /// The server's ID for this stream, for receiving it in
+    /// another client with [`Client::stream_from_id`][from_id].
+    /// `None` for a combined stream, which the server doesn't
+    /// know of.
+    ///
+    /// The ID stays valid only while this stream exists, as
+    /// dropping it removes the stream from the server.
+    ///
+    /// [from_id]: crate::Client::stream_from_id
+    pub fn id(&self) -> Option<u64> {
+        match self.key {
+            StreamKey::Server(id) => Some(id),
+            StreamKey::Combined(_) => None,
+        }
+    }
+
+    /// Set the update rate for this streaming procedure, in
+    /// updates per second.
+    ///
+    /// The server evaluates streams once per game update, so
+    /// the rate can only lower how often values are sent; a
+    /// rate of `0`, the default, sends every update.
     pub fn set_rate(&self, hz: f32) -> Result<(), RpcError> {
-        self.krpc.set_stream_rate(self.id, hz)
+        for id in &self.ids {
+            self.krpc.set_stream_rate(*id, hz)?;
+        }
+        Ok(())
+    }
+
+    /// Start a stream that was added without being started.
+    ///
+    /// Streams from the `_stream()` procedures start as soon as
+    /// they are created. kRPC has no way to pause a stream once
+    /// started; drop it instead.
+    pub fn start(&self) -> Result<(), RpcError> {
+        for id in &self.ids {
+            self.krpc.start_stream(*id)?;
+        }
+        Ok(())
     }
 
     /// Retrieve the current result received for this
@@ -114,24 +520,203 @@ impl<T: RpcType> Stream<T> {
     /// changed since the last call to [`get`][get]. Use
     /// [`wait`][wait] to block until the value has changed.
     ///
+    /// Blocks until the first result arrives, for a stream
+    /// that hasn't received one yet. Streams from `_stream()`
+    /// procedures and [`Client::add_stream`][add_stream]
+    /// already have, but one that was never started won't
+    /// until it is, so `get` would block forever. If the stream
+    /// connection closes first, `get` fails with
+    /// [`RpcError::Connection`].
+    ///
     /// [wait]: Stream::wait
     /// [get]: Stream::get
+    /// [add_stream]: crate::Client::add_stream
     pub fn get(&self) -> Result<T, RpcError> {
-        self.client.read_stream(self.id)
+        self.latest(None)?.ok_or(RpcError::Client)
+    }
+
+    /// Retrieve the current result, or `None` if none has
+    /// arrived yet. Never blocks.
+    pub fn try_get(&self) -> Result<Option<T>, RpcError> {
+        self.latest(Some(Duration::ZERO))
+    }
+
+    /// Retrieve the current result, waiting up to `timeout`
+    /// for the first to arrive. Returns `None` if none did.
+    pub fn get_timeout(
+        &self,
+        timeout: Duration,
+    ) -> Result<Option<T>, RpcError> {
+        self.latest(Some(timeout))
+    }
+
+    /// Iterate over the stream's values as they arrive,
+    /// blocking for each. The first is the current value.
+    ///
+    /// Only the latest value is kept, so if updates arrive
+    /// faster than they are consumed, the values in between
+    /// are skipped rather than queued.
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```no_run")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{services::space_center::SpaceCenter, Client};
+    /// # let client = Client::new("Test KRPC", "127.0.0.1", 50000, 50001)?;
+    /// let space_center = SpaceCenter::new(client);
+    /// let flight = space_center.active_vessel()?.flight(None)?;
+    /// for altitude in flight.mean_altitude_stream()?.iter().take(100) {
+    ///     println!("{}", altitude?);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            stream: self,
+            seen: 0,
+        }
+    }
+
+    /// Iterate over the values that have arrived, without
+    /// blocking. Only the latest value is kept, so this
+    /// yields at most that one, and only once it has arrived.
+    ///
+    #[cfg_attr(feature = "space_center", doc = "```")]
+    #[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+    /// # fn main() -> Result<(), krpc_client::error::RpcError> {
+    /// use krpc_client::{
+    ///     services::space_center::SpaceCenter, transport::MockClient,
+    /// };
+    /// let mock = MockClient::new();
+    /// mock.respond("SpaceCenter", "get_UT", 10.0)?;
+    /// let ut = SpaceCenter::new(mock.client()).ut_stream()?;
+    ///
+    /// let mut values = ut.try_iter();
+    /// assert_eq!(values.next().transpose()?, Some(10.0));
+    /// assert_eq!(values.next().transpose()?, None);
+    ///
+    /// mock.respond("SpaceCenter", "get_UT", 20.0)?;
+    /// mock.update_streams()?;
+    /// assert_eq!(values.next().transpose()?, Some(20.0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn try_iter(&self) -> TryIter<'_, T> {
+        TryIter {
+            stream: self,
+            seen: 0,
+        }
+    }
+
+    fn latest(&self, timeout: Option<Duration>) -> Result<Option<T>, RpcError> {
+        self.client
+            .await_stream_until(self.key, timeout, |_: &T| true)
     }
 
     /// Block the current thread of execution until this
     /// stream receives an update from the server.
     pub fn wait(&self) {
-        self.client.await_stream(self.id);
+        self.client.await_stream(self.key);
+    }
+}
+
+/// An iterator over a stream's values, blocking for each.
+/// Created by [`Stream::iter`].
+pub struct Iter<'a, T: RpcType> {
+    stream: &'a Stream<T>,
+    seen: u64,
+}
+
+impl<T: RpcType> Iterator for Iter<'_, T> {
+    type Item = Result<T, RpcError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_value(self.stream, &mut self.seen, None)
+    }
+}
+
+/// An iterator over a stream's values that doesn't block.
+/// Created by [`Stream::try_iter`].
+pub struct TryIter<'a, T: RpcType> {
+    stream: &'a Stream<T>,
+    seen: u64,
+}
+
+impl<T: RpcType> Iterator for TryIter<'_, T> {
+    type Item = Result<T, RpcError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        next_value(self.stream, &mut self.seen, Some(Duration::ZERO))
+    }
+}
+
+/// Wait for a value newer than the last `seen`.
+fn next_value<T: RpcType>(
+    stream: &Stream<T>,
+    seen: &mut u64,
+    timeout: Option<Duration>,
+) -> Option<Result<T, RpcError>> {
+    match stream.client.await_stream_newer(stream.key, *seen, timeout) {
+        Ok(Some((value, updates))) => {
+            *seen = updates;
+            Some(Ok(value))
+        }
+        Ok(None) => None,
+        Err(e) => Some(Err(e)),
+    }
+}
+
+impl Event {
+    /// Block the current thread of execution until the event
+    /// fires.
+    pub fn wait(&self) -> Result<(), RpcError> {
+        self.wait_until(None).map(drop)
+    }
+
+    /// Block the current thread of execution until the event
+    /// fires, or `timeout` elapses. Returns whether the event
+    /// fired.
+    pub fn wait_timeout(&self, timeout: Duration) -> Result<bool, RpcError> {
+        self.wait_until(Some(timeout))
+    }
+
+    fn wait_until(&self, timeout: Option<Duration>) -> Result<bool, RpcError> {
+        self.stream.client.check_streams()?;
+        if !self.started.swap(true, Ordering::SeqCst) {
+            if let Err(e) = self.stream.start() {
+                self.started.store(false, Ordering::SeqCst);
+                return Err(e);
+            }
+        }
+        self.stream
+            .client
+            .await_stream_until(self.stream.key, timeout, |fired: &bool| *fired)
+            .map(|fired| fired.is_some())
+    }
+}
+
+impl DecodeUntagged for Event {
+    fn decode_untagged(
+        client: ClientHandle,
+        buf: &[u8],
+    ) -> Result<Self, RpcError> {
+        let event = schema::Event::decode_untagged(client.clone(), buf)?;
+        Ok(Self {
+            stream: Stream::from_id(client, event.stream.id)?,
+            started: AtomicBool::new(false),
+        })
     }
 }
 
 impl<T: crate::RpcType> Drop for Stream<T> {
-    // Try to remove the stream if it's dropped, but don't panic
-    // if unable.
+    // Try to remove the stream once its last handle is
+    // dropped, but don't panic if unable.
     fn drop(&mut self) {
-        self.krpc.remove_stream(self.id).ok();
-        self.client.remove_stream(self.id).ok();
+        if let StreamKey::Combined(id) = self.key {
+            self.client.remove_combined_stream(id).ok();
+        }
+        for id in &self.ids {
+            self.client.remove_stream(*id).ok();
+        }
     }
 }
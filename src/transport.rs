@@ -0,0 +1,292 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+};
+
+use protobuf::{CodedInputStream, Message};
+
+pub use crate::schema::{
+    ProcedureCall, ProcedureResult, Request, Response, StreamResult,
+    StreamUpdate,
+};
+use crate::{
+    client::{Client, ClientHandle},
+    error::RpcError,
+    schema::{self, EncodeUntagged},
+    stream::StreamWrangler,
+};
+
+/// Carries procedure calls to a kRPC server, and its responses
+/// back.
+///
+/// [`Client::new`] connects over TCP. Implement this trait to
+/// make calls some other way, then construct a client with
+/// [`Client::with_transport`].
+pub trait RpcTransport: Send + Sync {
+    /// Send a request, returning the server's response. The
+    /// response must hold one result for each call in the
+    /// request, in the same order.
+    fn call(&self, request: &Request) -> Result<Response, RpcError>;
+
+    /// Called once by [`Client::with_transport`] with where to
+    /// deliver stream updates. Transports without streams can
+    /// ignore it, as the default does.
+    fn connect_streams(&self, _sink: StreamSink) {}
+}
+
+/// Delivers stream updates to a [`Client`] with a custom
+/// transport, as the server does over the stream connection.
+///
+/// Dropping every clone closes the client's streams, as the
+/// server closing the connection does: they keep their last
+/// values, but waiting for another fails with
+/// [`RpcError::Connection`].
+#[derive(Clone)]
+pub struct StreamSink {
+    connection: Arc<SinkConnection>,
+}
+
+struct SinkConnection {
+    streams: Arc<StreamWrangler>,
+    generation: u64,
+}
+
+impl StreamSink {
+    pub(crate) fn new(streams: Arc<StreamWrangler>) -> Self {
+        let generation = streams.open();
+        Self {
+            connection: Arc::new(SinkConnection {
+                streams,
+                generation,
+            }),
+        }
+    }
+
+    /// Update the client's streams with a batch of results.
+    pub fn send(&self, update: StreamUpdate) -> Result<(), RpcError> {
+        self.connection.streams.update(update)
+    }
+}
+
+impl Drop for SinkConnection {
+    fn drop(&mut self) {
+        self.streams.close(self.generation);
+    }
+}
+
+/// A transport answering calls with canned results, for
+/// testing code that uses the client without running a server.
+///
+/// Results are set per procedure with [`respond`][respond].
+/// Procedures without one return an empty result, which
+/// decodes as `()` or an empty collection. Class instances are
+/// returned by their ID, as a `u64`.
+///
+/// Streams are simulated: each is sent its procedure's result
+/// when added, and again on every call to
+/// [`update_streams`][update_streams].
+///
+#[cfg_attr(feature = "space_center", doc = "```")]
+#[cfg_attr(not(feature = "space_center"), doc = "```ignore")]
+/// # fn main() -> Result<(), krpc_client::error::RpcError> {
+/// use krpc_client::{
+///     services::space_center::SpaceCenter, transport::MockClient,
+/// };
+/// let mock = MockClient::new();
+/// mock.respond("SpaceCenter", "get_ActiveVessel", 1u64)?;
+/// mock.respond("SpaceCenter", "Vessel_get_Control", 2u64)?;
+///
+/// let space_center = SpaceCenter::new(mock.client());
+/// let vessel = space_center.active_vessel()?;
+/// assert_eq!(vessel.object_id(), 1);
+/// assert_eq!(format!("{vessel:?}"), "Vessel(1)");
+/// vessel.control()?.activate_next_stage()?;
+/// vessel.set_name("Apollo")?;
+///
+/// assert!(mock.called("SpaceCenter", "Control_ActivateNextStage"));
+/// assert!(mock.called("SpaceCenter", "Vessel_set_Name"));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [respond]: MockClient::respond
+/// [update_streams]: MockClient::update_streams
+#[derive(Clone, Default)]
+pub struct MockClient {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Default)]
+struct MockState {
+    results: HashMap<(String, String), Vec<u8>>,
+    calls: Vec<ProcedureCall>,
+    streams: BTreeMap<u64, ProcedureCall>,
+    next_stream_id: u64,
+    sinks: Vec<StreamSink>,
+}
+
+impl MockClient {
+    /// Constructs a new `MockClient` with no results set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs a [`Client`] making its calls through this
+    /// mock.
+    pub fn client(&self) -> ClientHandle {
+        Client::with_transport(self.clone())
+    }
+
+    /// Return `value` from every call to `service.procedure`.
+    pub fn respond<T: EncodeUntagged>(
+        &self,
+        service: &str,
+        procedure: &str,
+        value: T,
+    ) -> Result<(), RpcError> {
+        let value = value.encode_untagged()?;
+        self.state
+            .lock()?
+            .results
+            .insert((service.into(), procedure.into()), value);
+        Ok(())
+    }
+
+    /// Send every stream the current result of its procedure,
+    /// as the server does on each game update.
+    pub fn update_streams(&self) -> Result<(), RpcError> {
+        let state = self.state.lock()?;
+        let updates = state
+            .streams
+            .iter()
+            .map(|(id, call)| (*id, state.result(call)))
+            .collect();
+        state.send(updates)
+    }
+
+    /// Drop the sinks delivering stream updates, closing the
+    /// client's streams as losing the stream connection does.
+    pub fn disconnect_streams(&self) -> Result<(), RpcError> {
+        self.state.lock()?.sinks.clear();
+        Ok(())
+    }
+
+    /// Every call made so far, in order.
+    pub fn calls(&self) -> Result<Vec<ProcedureCall>, RpcError> {
+        Ok(self.state.lock()?.calls.clone())
+    }
+
+    /// Whether `service.procedure` has been called.
+    pub fn called(&self, service: &str, procedure: &str) -> bool {
+        self.calls()
+            .unwrap_or_default()
+            .iter()
+            .any(|call| call.service == service && call.procedure == procedure)
+    }
+}
+
+impl MockState {
+    /// The result set for a procedure.
+    fn result(&self, call: &ProcedureCall) -> Vec<u8> {
+        self.results
+            .get(&(call.service.clone(), call.procedure.clone()))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    fn call(&mut self, call: &ProcedureCall) -> Result<Vec<u8>, RpcError> {
+        match (call.service.as_str(), call.procedure.as_str()) {
+            ("KRPC", "AddStream") => {
+                let stream_call =
+                    ProcedureCall::parse_from_bytes(argument(call, 0))?;
+                // Like the server, reuse the stream of an
+                // identical call.
+                let existing = self
+                    .streams
+                    .iter()
+                    .find(|(_, call)| **call == stream_call)
+                    .map(|(id, _)| *id);
+                let id = existing.unwrap_or_else(|| {
+                    self.next_stream_id += 1;
+                    self.next_stream_id
+                });
+                self.send(vec![(id, self.result(&stream_call))])?;
+                self.streams.insert(id, stream_call);
+
+                schema::Stream {
+                    id,
+                    ..Default::default()
+                }
+                .encode_untagged()
+            }
+            ("KRPC", "RemoveStream") => {
+                let id = CodedInputStream::from_bytes(argument(call, 0))
+                    .read_uint64()?;
+                self.streams.remove(&id);
+                Ok(Vec::new())
+            }
+            _ => Ok(self.result(call)),
+        }
+    }
+
+    /// Send each stream in `updates` its new value.
+    fn send(&self, updates: Vec<(u64, Vec<u8>)>) -> Result<(), RpcError> {
+        let update = StreamUpdate {
+            results: updates
+                .into_iter()
+                .map(|(id, value)| StreamResult {
+                    id,
+                    result: Some(ProcedureResult {
+                        value,
+                        ..Default::default()
+                    })
+                    .into(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        for sink in &self.sinks {
+            sink.send(update.clone())?;
+        }
+        Ok(())
+    }
+}
+
+/// The encoded argument at `position`, or nothing if it was
+/// omitted.
+fn argument(call: &ProcedureCall, position: u32) -> &[u8] {
+    call.arguments
+        .iter()
+        .find(|argument| argument.position == position)
+        .map_or(&[], |argument| &argument.value)
+}
+
+impl RpcTransport for MockClient {
+    fn call(&self, request: &Request) -> Result<Response, RpcError> {
+        let mut state = self.state.lock()?;
+        state.calls.extend(request.calls.iter().cloned());
+
+        let results = request
+            .calls
+            .iter()
+            .map(|call| {
+                Ok(ProcedureResult {
+                    value: state.call(call)?,
+                    ..Default::default()
+                })
+            })
+            .collect::<Result<_, RpcError>>()?;
+
+        Ok(Response {
+            results,
+            ..Default::default()
+        })
+    }
+
+    fn connect_streams(&self, sink: StreamSink) {
+        if let Ok(mut state) = self.state.lock() {
+            state.sinks.push(sink);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+//! Client behaviour, against custom transports.
+
+use krpc_client::{
+    error::RpcError,
+    transport::{ProcedureCall, Request, Response, RpcTransport},
+    Client,
+};
+
+/// A transport answering every request with no results.
+struct Unanswered;
+
+impl RpcTransport for Unanswered {
+    fn call(&self, _request: &Request) -> Result<Response, RpcError> {
+        Ok(Response::default())
+    }
+}
+
+#[test]
+fn rejects_missing_results() {
+    let client = Client::with_transport(Unanswered);
+    let call = ProcedureCall {
+        service: "KRPC".into(),
+        procedure: "GetStatus".into(),
+        ..Default::default()
+    };
+    assert!(matches!(
+        client.batch(vec![call.clone(), call.clone()]),
+        Err(RpcError::Encoding(_))
+    ));
+    assert!(matches!(
+        client.call(Request::from(call)),
+        Err(RpcError::Encoding(_))
+    ));
+}
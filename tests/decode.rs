@@ -0,0 +1,172 @@
+//! Decoding malformed messages, which must fail rather than
+//! panic or hang.
+//!
+//! The inputs are generated deterministically, so that any
+//! failure reproduces.
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::{Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    thread,
+};
+
+use krpc_client::{
+    error::RpcError,
+    schema::{DecodeUntagged, ProcedureResult, Status, Tuple},
+    transport::{MockClient, ProcedureCall, Request},
+    Client, ClientHandle,
+};
+
+/// A xorshift generator, for inputs that are random but the
+/// same on every run.
+struct Bytes(u64);
+
+impl Bytes {
+    fn next(&mut self) -> u8 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0 as u8
+    }
+
+    fn take(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next()).collect()
+    }
+}
+
+fn varint(mut value: u64) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+    bytes
+}
+
+fn length_delimited(message: &[u8]) -> Vec<u8> {
+    [varint(message.len() as u64), message.to_vec()].concat()
+}
+
+/// Buffers that are truncated, random, or claim more bytes
+/// than they hold.
+fn malformed() -> Vec<Vec<u8>> {
+    let mut bytes = Bytes(0x2545_f491_4f6c_dd1d);
+    let mut buffers = Vec::new();
+    // A length-delimited field, cut short at each byte.
+    let field = [&[0x0a][..], &varint(20), &bytes.take(20)].concat();
+    buffers.extend((1..field.len()).map(|len| field[..len].to_vec()));
+    // Lengths far beyond the buffer.
+    buffers.push([&[0x0a][..], &varint(u64::MAX)].concat());
+    buffers.push([&[0x0a][..], &varint(1 << 40), &[0; 4]].concat());
+    // A varint that never ends.
+    buffers.push(vec![0xff; 11]);
+    for len in 0..200 {
+        buffers.push(bytes.take(len % 50));
+    }
+    buffers
+}
+
+fn decode<T: DecodeUntagged>(client: &ClientHandle, buf: &[u8]) -> bool {
+    T::decode_untagged(client.clone(), buf).is_ok()
+}
+
+#[test]
+fn decodes_malformed_values() {
+    let client = MockClient::new().client();
+    for buf in malformed() {
+        decode::<u64>(&client, &buf);
+        decode::<f64>(&client, &buf);
+        decode::<String>(&client, &buf);
+        decode::<Vec<Vec<u8>>>(&client, &buf);
+        decode::<HashSet<u64>>(&client, &buf);
+        decode::<HashMap<String, Vec<f64>>>(&client, &buf);
+        decode::<(f64, f64, f64)>(&client, &buf);
+        decode::<Option<(bool, String)>>(&client, &buf);
+        decode::<ProcedureResult>(&client, &buf);
+        decode::<Status>(&client, &buf);
+        decode::<Tuple>(&client, &buf);
+    }
+
+    // Each is cut short, or claims more than it holds.
+    for buf in malformed().iter().take(23) {
+        assert!(!decode::<Vec<Vec<u8>>>(&client, buf), "{buf:?}");
+        assert!(!decode::<Tuple>(&client, buf), "{buf:?}");
+    }
+}
+
+/// Read a length-delimited message from `conn`, ignoring it.
+fn skip_message(conn: &mut TcpStream) {
+    let mut length = 0;
+    for shift in (0..).step_by(7) {
+        let mut byte = [0];
+        conn.read_exact(&mut byte).unwrap();
+        length |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut message = vec![0; length as usize];
+    conn.read_exact(&mut message).unwrap();
+}
+
+/// Connect a client to a server answering its first call with
+/// `response`, as raw bytes, then closing the connection.
+fn connect(response: Vec<u8>) -> ClientHandle {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    thread::spawn(move || {
+        // Both handshakes succeed with an empty response.
+        let (mut rpc, _) = listener.accept().unwrap();
+        skip_message(&mut rpc);
+        rpc.write_all(&length_delimited(&[])).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+        skip_message(&mut stream);
+        stream.write_all(&length_delimited(&[])).unwrap();
+
+        // The version check gets an empty status.
+        skip_message(&mut rpc);
+        rpc.write_all(&length_delimited(&[0x12, 0x00])).unwrap();
+
+        skip_message(&mut rpc);
+        rpc.write_all(&response).ok();
+        rpc.shutdown(Shutdown::Both).ok();
+    });
+
+    let rpc = TcpStream::connect(addr).unwrap();
+    let stream = TcpStream::connect(addr).unwrap();
+    Client::from_streams("decode", rpc, stream).unwrap()
+}
+
+fn call(client: &Client) -> Result<(), RpcError> {
+    let request = Request::from(ProcedureCall {
+        service: "KRPC".into(),
+        procedure: "GetStatus".into(),
+        ..Default::default()
+    });
+    client.call(request).map(drop)
+}
+
+#[test]
+fn receives_malformed_responses() {
+    // Framed correctly, but not a valid response.
+    for buf in malformed() {
+        call(&connect(length_delimited(&buf))).ok();
+    }
+
+    let mut bytes = Bytes(0x9e37_79b9_7f4a_7c15);
+    let framing = [
+        // Cut short.
+        [varint(100), bytes.take(10)].concat(),
+        // Longer than the client accepts.
+        varint(u64::MAX),
+        // A length that never ends.
+        vec![0xff; 11],
+        // Nothing at all.
+        Vec::new(),
+    ];
+    for response in framing {
+        assert!(call(&connect(response.clone())).is_err(), "{response:?}");
+    }
+}
@@ -0,0 +1,39 @@
+//! Remote errors, converted to the error enums of the services
+//! defining them.
+
+use krpc_client::{
+    error::{RemoteError, RpcError},
+    services::krpc::KRPCError,
+};
+
+fn remote_error(exception_service: &str, name: &str) -> RpcError {
+    RpcError::Remote(Box::new(RemoteError {
+        service: "SpaceCenter".into(),
+        procedure: "Control_ActivateNextStage".into(),
+        exception_service: exception_service.into(),
+        name: name.into(),
+        description: "No stages left".into(),
+        stack_trace: None,
+    }))
+}
+
+#[test]
+fn converts_service_errors() {
+    let error = remote_error("KRPC", "InvalidOperationException");
+    assert!(matches!(
+        KRPCError::try_from(error),
+        Ok(KRPCError::InvalidOperation(_)),
+    ));
+
+    // Errors from another service, or not from the server at
+    // all, are given back unchanged.
+    let error = remote_error("SpaceCenter", "InvalidOperationException");
+    assert!(matches!(
+        KRPCError::try_from(error),
+        Err(RpcError::Remote(_)),
+    ));
+    assert!(matches!(
+        KRPCError::try_from(RpcError::Timeout),
+        Err(RpcError::Timeout),
+    ));
+}
@@ -0,0 +1,418 @@
+#[allow(clippy::type_complexity, clippy::mutable_key_type, clippy::double_must_use)]
+pub mod fixture {
+    use krpc_client::{schema::ToArgument, error::RpcError};
+    pub struct Fixture {
+        pub client: krpc_client::ClientHandle,
+    }
+    impl Fixture {
+        pub fn new(client: krpc_client::ClientHandle) -> Self {
+            Self { client }
+        }
+    }
+    krpc_client::schema::rpc_object!(Part);
+    krpc_client::schema::rpc_enum!(Kind, [None = 0i32, Type = 1i32,]);
+    impl Fixture {
+        pub fn find_call(
+            &self,
+            name: &str,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Find",
+                    vec![name.to_argument(0u32) ?],
+                ),
+            )
+        }
+        pub fn find_stream(
+            &self,
+            name: &str,
+        ) -> Result<
+            krpc_client::stream::Stream<Option<super::fixture::Part>>,
+            RpcError,
+        > {
+            self.client.add_stream(self.find_call(name)?)
+        }
+        /// A string parameter.
+        #[must_use]
+        pub fn find(
+            &self,
+            name: &str,
+        ) -> Result<Option<super::fixture::Part>, RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.find_call(name)?))
+        }
+    }
+    impl Fixture {
+        pub fn launch_call(
+            &self,
+            stage: u32,
+            delay: Option<f32>,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Launch",
+                    vec![
+                        Some(stage.to_argument(0u32) ?), delay.map(| v | v
+                        .to_argument(1u32)).transpose() ?
+                    ]
+                        .into_iter()
+                        .flatten()
+                        .collect(),
+                ),
+            )
+        }
+        pub fn launch_stream(
+            &self,
+            stage: u32,
+            delay: Option<f32>,
+        ) -> Result<krpc_client::stream::Stream<()>, RpcError> {
+            self.client.add_stream(self.launch_call(stage, delay)?)
+        }
+        /// Launches from `stage`, after an optional delay.
+        pub fn launch(&self, stage: u32, delay: Option<f32>) -> Result<(), RpcError> {
+            self.client
+                .invoke(
+                    krpc_client::schema::Request::from(self.launch_call(stage, delay)?),
+                )
+        }
+    }
+    impl Part {
+        pub fn name_2_call(
+            &self,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Part_Name",
+                    vec![self.to_argument(0u32) ?],
+                ),
+            )
+        }
+        pub fn name_2_stream(
+            &self,
+        ) -> Result<krpc_client::stream::Stream<String>, RpcError> {
+            self.client.add_stream(self.name_2_call()?)
+        }
+        /// A method whose name collides with a property.
+        #[must_use]
+        pub fn name_2(&self) -> Result<String, RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.name_2_call()?))
+        }
+    }
+    impl Part {
+        pub fn offset_call(
+            &self,
+            by: i64,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Part_Offset",
+                    vec![self.to_argument(0u32) ?, by.to_argument(1u32) ?],
+                ),
+            )
+        }
+        pub fn offset_stream(
+            &self,
+            by: i64,
+        ) -> Result<krpc_client::stream::Stream<i64>, RpcError> {
+            self.client.add_stream(self.offset_call(by)?)
+        }
+        /// A signed 64-bit integer, which may be < 0.
+        #[must_use]
+        pub fn offset(&self, by: i64) -> Result<i64, RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.offset_call(by)?))
+        }
+    }
+    impl Part {
+        pub fn id_call(&self) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Part_get_Id",
+                    vec![self.to_argument(0u32) ?],
+                ),
+            )
+        }
+        pub fn id_stream(&self) -> Result<krpc_client::stream::Stream<u64>, RpcError> {
+            self.client.add_stream(self.id_call()?)
+        }
+        /// An unsigned 64-bit integer.
+        #[must_use]
+        pub fn id(&self) -> Result<u64, RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.id_call()?))
+        }
+    }
+    impl Part {
+        pub fn name_call(&self) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Part_get_Name",
+                    vec![self.to_argument(0u32) ?],
+                ),
+            )
+        }
+        pub fn name_stream(
+            &self,
+        ) -> Result<krpc_client::stream::Stream<String>, RpcError> {
+            self.client.add_stream(self.name_call()?)
+        }
+        /// The name of the part.
+        #[must_use]
+        pub fn name(&self) -> Result<String, RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.name_call()?))
+        }
+    }
+    impl Part {
+        pub fn stage_call(
+            &self,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Part_get_Stage",
+                    vec![self.to_argument(0u32) ?],
+                ),
+            )
+        }
+        pub fn stage_stream(
+            &self,
+        ) -> Result<krpc_client::stream::Stream<u32>, RpcError> {
+            self.client.add_stream(self.stage_call()?)
+        }
+        /// An unsigned 32-bit integer.
+        #[must_use]
+        pub fn stage(&self) -> Result<u32, RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.stage_call()?))
+        }
+    }
+    impl Fixture {
+        pub fn place_call(
+            &self,
+            x: f64,
+            y: f64,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Place",
+                    vec![x.to_argument(0u32) ?, y.to_argument(2u32) ?],
+                ),
+            )
+        }
+        pub fn place_stream(
+            &self,
+            x: f64,
+            y: f64,
+        ) -> Result<krpc_client::stream::Stream<()>, RpcError> {
+            self.client.add_stream(self.place_call(x, y)?)
+        }
+        /// Parameters at positions that are not contiguous.
+        pub fn place(&self, x: f64, y: f64) -> Result<(), RpcError> {
+            self.client
+                .invoke(krpc_client::schema::Request::from(self.place_call(x, y)?))
+        }
+    }
+    impl Fixture {
+        pub fn reset_call(
+            &self,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(krpc_client::client::Client::proc_call("Fixture", "Reset", vec![]))
+        }
+        pub fn reset_stream(&self) -> Result<krpc_client::stream::Stream<()>, RpcError> {
+            self.client.add_stream(self.reset_call()?)
+        }
+        /// A return type of NONE.
+        pub fn reset(&self) -> Result<(), RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.reset_call()?))
+        }
+    }
+    impl Fixture {
+        pub fn select_call(
+            &self,
+            r#type: super::fixture::Kind,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Select",
+                    vec![r#type.to_argument(0u32) ?],
+                ),
+            )
+        }
+        pub fn select_stream(
+            &self,
+            r#type: super::fixture::Kind,
+        ) -> Result<krpc_client::stream::Stream<()>, RpcError> {
+            self.client.add_stream(self.select_call(r#type)?)
+        }
+        /// A parameter named after a keyword, and no return type.
+        pub fn select(&self, r#type: super::fixture::Kind) -> Result<(), RpcError> {
+            self.client
+                .invoke(krpc_client::schema::Request::from(self.select_call(r#type)?))
+        }
+    }
+    impl Fixture {
+        pub fn transform_call(
+            &self,
+            values: (f64, f64, f64, f64, f64),
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Fixture",
+                    "Transform",
+                    vec![values.to_argument(0u32) ?],
+                ),
+            )
+        }
+        pub fn transform_stream(
+            &self,
+            values: (f64, f64, f64, f64, f64),
+        ) -> Result<krpc_client::stream::Stream<(f64, f64, f64, f64)>, RpcError> {
+            self.client.add_stream(self.transform_call(values)?)
+        }
+        /// Longer tuples.
+        #[must_use]
+        pub fn transform(
+            &self,
+            values: (f64, f64, f64, f64, f64),
+        ) -> Result<(f64, f64, f64, f64), RpcError> {
+            self.client
+                .invoke(krpc_client::schema::Request::from(self.transform_call(values)?))
+        }
+    }
+    impl Fixture {
+        pub fn groups_call(
+            &self,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(krpc_client::client::Client::proc_call("Fixture", "get_Groups", vec![]))
+        }
+        pub fn groups_stream(
+            &self,
+        ) -> Result<
+            krpc_client::stream::Stream<
+                Vec<::std::collections::HashMap<String, Vec<super::fixture::Part>>>,
+            >,
+            RpcError,
+        > {
+            self.client.add_stream(self.groups_call()?)
+        }
+        /// Nested collections.
+        #[must_use]
+        pub fn groups(
+            &self,
+        ) -> Result<
+            Vec<::std::collections::HashMap<String, Vec<super::fixture::Part>>>,
+            RpcError,
+        > {
+            self.client.invoke(krpc_client::schema::Request::from(self.groups_call()?))
+        }
+    }
+    impl Fixture {
+        pub fn parts_call(
+            &self,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(krpc_client::client::Client::proc_call("Fixture", "get_Parts", vec![]))
+        }
+        pub fn parts_stream(
+            &self,
+        ) -> Result<
+            krpc_client::stream::Stream<
+                ::std::collections::HashSet<super::fixture::Part>,
+            >,
+            RpcError,
+        > {
+            self.client.add_stream(self.parts_call()?)
+        }
+        /// A set of classes.
+        #[must_use]
+        pub fn parts(
+            &self,
+        ) -> Result<::std::collections::HashSet<super::fixture::Part>, RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.parts_call()?))
+        }
+    }
+}
+#[allow(clippy::type_complexity, clippy::mutable_key_type, clippy::double_must_use)]
+pub mod other {
+    use krpc_client::{schema::ToArgument, error::RpcError};
+    pub struct Other {
+        pub client: krpc_client::ClientHandle,
+    }
+    impl Other {
+        pub fn new(client: krpc_client::ClientHandle) -> Self {
+            Self { client }
+        }
+    }
+    impl Other {
+        pub fn attach_call(
+            &self,
+            part: &super::fixture::Part,
+        ) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(
+                krpc_client::client::Client::proc_call(
+                    "Other",
+                    "Attach",
+                    vec![part.to_argument(0u32) ?],
+                ),
+            )
+        }
+        pub fn attach_stream(
+            &self,
+            part: &super::fixture::Part,
+        ) -> Result<krpc_client::stream::Stream<()>, RpcError> {
+            self.client.add_stream(self.attach_call(part)?)
+        }
+        /// A parameter of a class from another service.
+        pub fn attach(&self, part: &super::fixture::Part) -> Result<(), RpcError> {
+            self.client
+                .invoke(krpc_client::schema::Request::from(self.attach_call(part)?))
+        }
+    }
+    impl Other {
+        pub fn root_call(&self) -> Result<krpc_client::schema::ProcedureCall, RpcError> {
+            Ok(krpc_client::client::Client::proc_call("Other", "get_Root", vec![]))
+        }
+        pub fn root_stream(
+            &self,
+        ) -> Result<krpc_client::stream::Stream<super::fixture::Part>, RpcError> {
+            self.client.add_stream(self.root_call()?)
+        }
+        /// A class from another service.
+        #[must_use]
+        pub fn root(&self) -> Result<super::fixture::Part, RpcError> {
+            self.client.invoke(krpc_client::schema::Request::from(self.root_call()?))
+        }
+    }
+}
+/// Every RPC service, constructed from a single client.
+///
+/// Each accessor is cheap, only cloning the client.
+#[derive(Clone)]
+pub struct Services {
+    client: krpc_client::ClientHandle,
+}
+impl Services {
+    pub fn new(client: krpc_client::ClientHandle) -> Self {
+        Self { client }
+    }
+    /// A service exercising the generator.
+    pub fn fixture(&self) -> fixture::Fixture {
+        fixture::Fixture::new(self.client.clone())
+    }
+    /// A service using another's classes.
+    pub fn other(&self) -> other::Other {
+        other::Other::new(self.client.clone())
+    }
+}
+/// Every service, and the enumerations they define.
+pub mod prelude {
+    pub use super::fixture::Fixture;
+    pub use super::fixture::Kind;
+    pub use super::other::Other;
+}
+/// The kRPC version these services were generated from,
+/// if known.
+pub const KRPC_VERSION: Option<&str> = None;
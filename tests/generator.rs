@@ -0,0 +1,185 @@
+//! Snapshot tests for the service generator, run against the
+//! definitions in `tests/fixtures/definitions`.
+//!
+//! The snapshot is checked in as `tests/fixtures/services.rs`.
+//! Set `UPDATE_SNAPSHOTS=1` to regenerate it after an intended
+//! change to the output.
+
+use std::collections::{HashMap, HashSet};
+
+use krpc_client::{
+    error::RpcError,
+    transport::{MockClient, ProcedureCall},
+};
+
+// Each test uses only part of the generator.
+#[allow(dead_code)]
+#[path = "../krpc_build/mod.rs"]
+mod krpc_build;
+
+// The snapshot, compiled against the client to check that it
+// builds as well as matches.
+#[allow(dead_code, unused_imports)]
+mod services {
+    include!("fixtures/services.rs");
+}
+
+use services::{
+    fixture::{Fixture, Kind, Part},
+    other::Other,
+};
+
+const DEFINITIONS: &str = "tests/fixtures/definitions";
+
+fn config() -> krpc_build::BuildConfig {
+    krpc_build::BuildConfig {
+        ungated_services: true,
+        runtime_path: Some("krpc_client".into()),
+        ..Default::default()
+    }
+}
+
+fn generate() -> String {
+    let mut out = Vec::new();
+    krpc_build::build(DEFINITIONS, &config(), &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// The error from generating `tests/fixtures/invalid/{name}.json`.
+fn generate_invalid(name: &str) -> String {
+    let path = format!("tests/fixtures/invalid/{name}.json");
+    match krpc_build::build_from_file(&path, &config(), &mut std::io::sink()) {
+        Ok(_) => panic!("{path} generated without error"),
+        Err(e) => e.to_string(),
+    }
+}
+
+/// The position of each of `call`'s arguments.
+fn positions(call: &ProcedureCall) -> Vec<u32> {
+    call.arguments
+        .iter()
+        .map(|argument| argument.position)
+        .collect()
+}
+
+// Unformatted output is one line, so only the formatted output
+// is worth comparing.
+#[cfg(feature = "fmt")]
+#[test]
+fn matches_snapshot() {
+    const SNAPSHOT: &str = "tests/fixtures/services.rs";
+
+    let generated = generate();
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        std::fs::write(SNAPSHOT, &generated).unwrap();
+    }
+    let snapshot = std::fs::read_to_string(SNAPSHOT).unwrap();
+    assert!(
+        generated == snapshot,
+        "generated services differ from {SNAPSHOT}; rerun with \
+         UPDATE_SNAPSHOTS=1 to update it"
+    );
+}
+
+#[test]
+fn is_deterministic() {
+    assert_eq!(generate(), generate());
+}
+
+#[test]
+fn generates_fixture_api() -> Result<(), RpcError> {
+    let mock = MockClient::new();
+    mock.respond("Fixture", "Find", 1u64)?;
+    mock.respond("Fixture", "Part_get_Id", u64::MAX)?;
+    mock.respond("Fixture", "Part_get_Stage", 2u32)?;
+    mock.respond("Fixture", "Part_Offset", -1i64)?;
+    mock.respond("Fixture", "Part_get_Name", "tank".to_string())?;
+    mock.respond("Fixture", "Part_Name", "Tank".to_string())?;
+    mock.respond("Fixture", "Transform", (1.0, 2.0, 3.0, 4.0))?;
+    let fixture = Fixture::new(mock.client());
+
+    // A set of classes.
+    let _: HashSet<Part> = fixture.parts()?;
+    // Collections nested in each other.
+    let _: Vec<HashMap<String, Vec<Part>>> = fixture.groups()?;
+    // A keyword parameter, and enumeration values that need
+    // rewriting, with no result.
+    let () = fixture.select(Kind::None)?;
+    let () = fixture.select(Kind::Type)?;
+    // A return type of `NONE`, also with no result.
+    let () = fixture.reset()?;
+    // Tuples of four and five elements.
+    let values = (0.0, 1.0, 2.0, 3.0, 4.0);
+    assert_eq!(fixture.transform(values)?, (1.0, 2.0, 3.0, 4.0));
+    // Arguments at the positions the definition gives.
+    assert_eq!(positions(&fixture.place_call(1.0, 2.0)?), [0, 2]);
+    // A defaulted parameter, sent only when given.
+    assert_eq!(positions(&fixture.launch_call(1, None)?), [0]);
+    assert_eq!(positions(&fixture.launch_call(1, Some(2.0))?), [0, 1]);
+    // A string parameter, taken by reference.
+    let part: Part = fixture.find("tank")?.expect("a part");
+
+    assert_eq!(part.id()?, u64::MAX);
+    assert_eq!(part.stage()?, 2);
+    assert_eq!(part.offset(1)?, -1);
+    // Procedures generating the same method name.
+    assert_eq!(part.name()?, "tank");
+    assert_eq!(part.name_2()?, "Tank");
+    Ok(())
+}
+
+#[test]
+fn shares_classes_across_services() -> Result<(), RpcError> {
+    let mock = MockClient::new();
+    mock.respond("Other", "get_Root", 1u64)?;
+    let other = Other::new(mock.client());
+
+    let root: Part = other.root()?;
+    other.attach(&root)?;
+    assert!(mock.called("Other", "Attach"));
+    Ok(())
+}
+
+#[test]
+fn rejects_colliding_variants() {
+    let error = generate_invalid("KRPC.EnumCollision");
+    assert!(error.contains("`Type` and `type`"), "{error}");
+
+    let error = generate_invalid("KRPC.ErrorCollision");
+    assert!(error.contains("`Foo` and `FooException`"), "{error}");
+}
+
+#[test]
+fn rejects_colliding_positions() {
+    let error = generate_invalid("KRPC.PositionCollision");
+    assert!(error.contains("`y` reuses position 1"), "{error}");
+}
+
+#[test]
+fn rejects_undefined_cached_procedures() {
+    let config = krpc_build::BuildConfig {
+        cached_procedures: ["Fixture.get_Parts", "Fixture.get_Part"]
+            .map(String::from)
+            .into(),
+        ..config()
+    };
+    let error = krpc_build::build(DEFINITIONS, &config, &mut std::io::sink())
+        .unwrap_err()
+        .to_string();
+    assert_eq!(error, "cached procedures are not defined: Fixture.get_Part");
+}
+
+#[test]
+fn rejects_undefined_memoized_procedures() {
+    let config = krpc_build::BuildConfig {
+        memoized_procedures: ["Fixture.get_Part".into()].into(),
+        ..config()
+    };
+    let error = krpc_build::build(DEFINITIONS, &config, &mut std::io::sink())
+        .unwrap_err()
+        .to_string();
+    assert_eq!(
+        error,
+        "memoized procedures are not defined: Fixture.get_Part"
+    );
+}
@@ -0,0 +1,94 @@
+//! The generated services, against a [`MockClient`].
+
+#![cfg(feature = "space_center")]
+
+use krpc_client::{
+    error::RpcError,
+    services::space_center::{SpaceCenter, Vessel, VesselSituation},
+    transport::MockClient,
+};
+
+/// The active vessel of `mock`, with ID 1.
+fn active_vessel(mock: &MockClient) -> Result<Vessel, RpcError> {
+    mock.respond("SpaceCenter", "get_ActiveVessel", 1u64)?;
+    SpaceCenter::new(mock.client()).active_vessel()
+}
+
+// A newer server might send a value the enum doesn't have.
+#[test]
+fn decodes_unknown_enum_values() -> Result<(), RpcError> {
+    let mock = MockClient::new();
+    let vessel = active_vessel(&mock)?;
+
+    mock.respond("SpaceCenter", "Vessel_get_Situation", 5i32)?;
+    assert_eq!(vessel.situation()?, VesselSituation::Landed);
+
+    mock.respond("SpaceCenter", "Vessel_get_Situation", 99i32)?;
+    assert!(matches!(vessel.situation(), Err(RpcError::Encoding(_))));
+    Ok(())
+}
+
+// The server sends a missing object, such as the target vessel
+// when nothing is targeted, as ID 0.
+#[test]
+fn decodes_null_objects() -> Result<(), RpcError> {
+    let mock = MockClient::new();
+    let space_center = SpaceCenter::new(mock.client());
+
+    mock.respond("SpaceCenter", "get_TargetVessel", 0u64)?;
+    assert!(space_center.target_vessel()?.is_none());
+
+    mock.respond("SpaceCenter", "get_TargetVessel", 2u64)?;
+    let vessel = space_center.target_vessel()?.expect("a vessel");
+    assert_eq!(vessel.object_id(), 2);
+    Ok(())
+}
+
+// Objects are sent as their IDs, encoded as `uint64` varints,
+// so every ID makes it to the server and back unchanged.
+#[test]
+fn round_trips_object_ids() -> Result<(), RpcError> {
+    let mock = MockClient::new();
+    let space_center = SpaceCenter::new(mock.client());
+
+    for (id, encoded) in [
+        (1, vec![0x01]),
+        (300, vec![0xac, 0x02]),
+        (u64::MAX - 1, [&[0xfe][..], &[0xff; 8], &[0x01]].concat()),
+    ] {
+        mock.respond("SpaceCenter", "get_TargetVessel", id)?;
+        let vessel = space_center.target_vessel()?.expect("a vessel");
+        assert_eq!(vessel.object_id(), id);
+
+        let call = space_center.set_active_vessel_call(&vessel)?;
+        assert_eq!(call.arguments[0].value, encoded);
+    }
+    Ok(())
+}
+
+// Tuples, such as vectors, are sent as `Tuple` messages holding
+// each encoded element.
+#[cfg(feature = "drawing")]
+#[test]
+fn encodes_tuples() -> Result<(), RpcError> {
+    use krpc_client::services::drawing::Drawing;
+
+    let mock = MockClient::new();
+    mock.respond("SpaceCenter", "Vessel_get_ReferenceFrame", 2u64)?;
+    let frame = active_vessel(&mock)?.reference_frame()?;
+
+    let call = Drawing::new(mock.client()).add_direction_call(
+        (1.0, 0.0, -2.0),
+        &frame,
+        None,
+        None,
+    )?;
+    let mut expected = Vec::new();
+    for element in [1.0f64, 0.0, -2.0] {
+        // Field 1, `items`, of 8 bytes.
+        expected.extend([0x0a, 8]);
+        expected.extend(element.to_le_bytes());
+    }
+    assert_eq!(call.arguments[0].value, expected);
+    Ok(())
+}
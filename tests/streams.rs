@@ -0,0 +1,37 @@
+//! Stream behaviour, against a [`MockClient`].
+
+use krpc_client::{
+    error::RpcError,
+    transport::{MockClient, ProcedureCall},
+};
+
+fn call(procedure: &str) -> ProcedureCall {
+    ProcedureCall {
+        service: "SpaceCenter".into(),
+        procedure: procedure.into(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn closed_streams_stop_waiting() -> Result<(), RpcError> {
+    let mock = MockClient::new();
+    mock.respond("SpaceCenter", "get_UT", 10.0)?;
+    let client = mock.client();
+    let ut = client.add_stream::<f64>(call("get_UT"))?;
+    let unstarted = client.stream_from_id::<f64>(ut.id().unwrap() + 1)?;
+
+    mock.disconnect_streams()?;
+    // The last value is kept, but no more can arrive.
+    assert_eq!(ut.get()?, 10.0);
+    assert!(matches!(
+        ut.iter().nth(1),
+        Some(Err(RpcError::Connection(_)))
+    ));
+    assert!(matches!(unstarted.get(), Err(RpcError::Connection(_))));
+    assert!(matches!(
+        client.add_stream::<f64>(call("get_MET")),
+        Err(RpcError::Connection(_))
+    ));
+    Ok(())
+}